@@ -1,7 +1,8 @@
 use criterion::{criterion_group, criterion_main, Criterion};
 use parrd_sampling::samplers::{
-    naive_sampler::NaiveSampler, perm_sampler::PermutationSampler,
-    priority_sampler::ParPrioritySampler, sampl_interface::Sampler,
+    hypergeometric_sampler::HypergeometricSampler, naive_sampler::NaiveSampler,
+    perm_sampler::PermutationSampler, priority_sampler::ParPrioritySampler,
+    sampl_interface::Sampler,
 };
 
 pub fn bench_single_core(c: &mut Criterion) {
@@ -19,17 +20,22 @@ pub fn bench_single_core(c: &mut Criterion) {
 
         c.bench_function(
             &format!("MultiNaive{}", (percentage * 100f32) as usize),
-            |b| b.iter(|| NaiveSampler::sample(&population, k)),
+            |b| b.iter(|| NaiveSampler::new().sample(&population, k)),
         );
 
         c.bench_function(
             &format!("MultiPriority{}", (percentage * 100f32) as usize),
-            |b| b.iter(|| ParPrioritySampler::sample(&population, k)),
+            |b| b.iter(|| ParPrioritySampler::new().sample(&population, k)),
         );
 
         c.bench_function(
             &format!("MultiPermutation{}", (percentage * 100f32) as usize),
-            |b| b.iter(|| PermutationSampler::sample(&population, k)),
+            |b| b.iter(|| PermutationSampler::new().sample(&population, k)),
+        );
+
+        c.bench_function(
+            &format!("MultiHypergeometric{}", (percentage * 100f32) as usize),
+            |b| b.iter(|| HypergeometricSampler::new().sample(&population, k)),
         );
     }
 }
@@ -19,17 +19,17 @@ pub fn bench_single_core(c: &mut Criterion) {
 
         c.bench_function(
             &format!("MultiHTNaive{}", (percentage * 100f32) as usize),
-            |b| b.iter(|| NaiveSampler::sample(&population, k)),
+            |b| b.iter(|| NaiveSampler::new().sample(&population, k)),
         );
 
         c.bench_function(
             &format!("MultiHTPriority{}", (percentage * 100f32) as usize),
-            |b| b.iter(|| ParPrioritySampler::sample(&population, k)),
+            |b| b.iter(|| ParPrioritySampler::new().sample(&population, k)),
         );
 
         c.bench_function(
             &format!("MultiHTPermutation{}", (percentage * 100f32) as usize),
-            |b| b.iter(|| PermutationSampler::sample(&population, k)),
+            |b| b.iter(|| PermutationSampler::new().sample(&population, k)),
         );
     }
 }
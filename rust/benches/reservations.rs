@@ -0,0 +1,132 @@
+use criterion::{criterion_group, criterion_main, Criterion};
+use parrd_sampling::{
+    samplers::{perm_sampler::PermutationSampler, sampl_interface::IndexSampler},
+    utils::reservations::{DenseReservations, Reservations, SparseReservations},
+};
+use rand::Rng;
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicU32, AtomicUsize, Ordering},
+};
+
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, keeping count of the live and peak heap bytes so
+/// every structure's footprint can be reported next to its timings.
+struct CountingAlloc;
+
+impl CountingAlloc {
+    fn grow(size: usize) {
+        let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+        PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+    }
+
+    fn shrink(size: usize) {
+        LIVE_BYTES.fetch_sub(size, Ordering::Relaxed);
+    }
+}
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = System.alloc(layout);
+        if !ptr.is_null() {
+            Self::grow(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout);
+        Self::shrink(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = System.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            Self::shrink(layout.size());
+            Self::grow(new_size);
+        }
+        new_ptr
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAlloc = CountingAlloc;
+
+/// Most heap bytes `f` had allocated at once, on top of what was live
+/// before it ran.
+fn peak_bytes<R>(f: impl FnOnce() -> R) -> usize {
+    let base = LIVE_BYTES.load(Ordering::Relaxed);
+    PEAK_BYTES.store(base, Ordering::Relaxed);
+    black_box(f());
+    PEAK_BYTES.load(Ordering::Relaxed) - base
+}
+
+/// Prints the peak allocation of one run of `f`, then times it.
+fn bench_with_memory<R>(c: &mut Criterion, name: &str, f: impl Fn() -> R) {
+    println!("{}: {} bytes allocated at peak", name, peak_bytes(&f));
+    c.bench_function(name, |b| b.iter(&f));
+}
+
+/// The reservation pattern of a permutation sample: rounds of 1% of the
+/// first `k` indices, each reserving itself and a random later slot.
+fn reserve_rounds<R: Reservations>(mut reservations: R, swap_targets: &[usize]) -> usize {
+    let k = swap_targets.len();
+    let round_len = (k / 100).max(100);
+    (0..k)
+        .step_by(round_len)
+        .map(|start| {
+            let round: Vec<usize> = (start..(start + round_len).min(k)).collect();
+            reservations.start_round(round.len());
+            round.par_iter().for_each(|&i| {
+                reservations.reserve(i, i);
+                reservations.reserve(swap_targets[i], i);
+            });
+            let won = round
+                .par_iter()
+                .filter(|&&i| reservations.holds(i, i) && reservations.holds(swap_targets[i], i))
+                .count();
+            let touched: Vec<usize> = if reservations.needs_touched() {
+                round.iter().flat_map(|&i| [i, swap_targets[i]]).collect()
+            } else {
+                vec![]
+            };
+            reservations.finish_round(&touched);
+            won
+        })
+        .sum()
+}
+
+pub fn bench_reservations(c: &mut Criterion) {
+    let n = 10_000_000;
+
+    for percentage in [1, 10, 50] {
+        let k = n / 100 * percentage;
+        let swap_targets: Vec<usize> = (0..k)
+            .into_par_iter()
+            .map_init(rand::thread_rng, |rng, i| rng.gen_range(i..n))
+            .collect();
+
+        bench_with_memory(c, &format!("DenseWide{}", percentage), || {
+            reserve_rounds(DenseReservations::<AtomicUsize>::new(n), &swap_targets)
+        });
+
+        bench_with_memory(c, &format!("DenseNarrow{}", percentage), || {
+            reserve_rounds(DenseReservations::<AtomicU32>::new(n), &swap_targets)
+        });
+
+        bench_with_memory(c, &format!("Sparse{}", percentage), || {
+            reserve_rounds(SparseReservations::new(), &swap_targets)
+        });
+
+        bench_with_memory(c, &format!("PermutationIndices{}", percentage), || {
+            PermutationSampler::new().sample_indices(n, k)
+        });
+    }
+}
+
+criterion_group!(benches, bench_reservations);
+criterion_main!(benches);
@@ -1,2 +1,6 @@
 pub mod samplers;
 pub mod utils;
+
+pub use samplers::perm_sampler::{
+    par_random_permutation, par_random_permutation_seeded, par_shuffle, par_shuffle_seeded,
+};
@@ -1,5 +1,5 @@
 use csv::Writer;
-use parrd_sampling::utils::my_bencher::benchmark_core;
+use parrd_sampling::{samplers::auto_sampler::calibrate, utils::my_bencher::benchmark_core};
 use std::error::Error;
 
 fn main() -> Result<(), Box<dyn Error>> {
@@ -17,6 +17,10 @@ fn main() -> Result<(), Box<dyn Error>> {
         let mut csv_result_wtr = Writer::from_path("analysis/results/multiht_core_result.csv")?;
         csv_result_wtr.write_record(["algorithm", "threads", "n", "k", "rep", "dtype", "time"])?;
         let _ = benchmark_core(&mut csv_result_wtr, 24);
+    } else if args[1] == "calibrate" {
+        let model = calibrate(1 << 22)?;
+        model.save("analysis/results/auto_cost_model.csv")?;
+        println!("{:?}", model);
     }
 
     Ok(())
@@ -0,0 +1,439 @@
+use std::{mem::size_of, path::Path, time::Instant};
+
+use rayon::iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator};
+
+use crate::samplers::{
+    floyd_sampler::{prefers_floyd, ParFloydSampler},
+    naive_sampler::NaiveSampler,
+    perm_sampler::PermutationSampler,
+    priority_sampler::ParPrioritySampler,
+    sampl_error::SamplingError,
+    sampl_interface::{Configurable, IndexSampler, Sampler, SamplerConfig, SamplerKind},
+};
+
+/// Runs of every calibration measurement; the fastest one counts.
+const CALIBRATION_REPEATS: usize = 3;
+
+/// Running time of one algorithm, `(per_n * n + per_k * k)` nanoseconds on
+/// one thread, of which the fraction `parallel` speeds up with more threads
+/// (Amdahl's law).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Cost {
+    per_n: f64,
+    per_k: f64,
+    parallel: f64,
+}
+
+impl Cost {
+    pub fn new(per_n: f64, per_k: f64, parallel: f64) -> Self {
+        Self {
+            per_n,
+            per_k,
+            parallel,
+        }
+    }
+
+    fn estimate(&self, n: f64, k: f64, threads: usize) -> f64 {
+        (self.per_n * n + self.per_k * k) * (1.0 - self.parallel + self.parallel / threads as f64)
+    }
+
+    /// Fits `per_n` and `per_k` to single-threaded times (ns) at `k_lo` and
+    /// `k_hi`, and `parallel` to the speedup seen with `threads` threads. A
+    /// single thread shows no speedup, so `parallel` stays at `fallback`'s.
+    fn fit(
+        n: usize,
+        (k_lo, k_hi): (usize, usize),
+        seq: [f64; 2],
+        par: [f64; 2],
+        threads: usize,
+        fallback: Cost,
+    ) -> Self {
+        let per_k = ((seq[1] - seq[0]) / (k_hi - k_lo) as f64).max(0.0);
+        let per_n = ((seq[0] - per_k * k_lo as f64) / n as f64).max(0.0);
+        let speedup = (seq[0] + seq[1]) / (par[0] + par[1]);
+        let parallel = if threads > 1 {
+            ((1.0 - 1.0 / speedup) / (1.0 - 1.0 / threads as f64)).clamp(0.0, 1.0)
+        } else {
+            fallback.parallel
+        };
+        Self::new(per_n, per_k, parallel)
+    }
+}
+
+/// What `AutoSampler` believes each candidate costs, for a population of
+/// `usize`. `copy` is the cost per byte of moving elements, which accounts
+/// for other element sizes.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CostModel {
+    naive: Cost,
+    priority: Cost,
+    permutation: Cost,
+    copy: Cost,
+}
+
+/// Rough figures in line with the thesis benchmarks; `calibrate` fits them
+/// to the machine at hand.
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            naive: Cost::new(25.0, 120.0, 0.0),
+            priority: Cost::new(9.0, 4.0, 0.95),
+            permutation: Cost::new(2.0, 220.0, 0.9),
+            copy: Cost::new(0.2, 0.0, 0.8),
+        }
+    }
+}
+
+impl CostModel {
+    /// Estimated nanoseconds for `kind` to pick `k` of `n` elements of
+    /// `elem_size` bytes on `threads` threads. `elem_size` is `None` when
+    /// only indices are drawn: nothing gets copied then, and permutation
+    /// only works on the at most `2k` slots its swaps touch.
+    pub fn estimate(
+        &self,
+        kind: SamplerKind,
+        n: usize,
+        k: usize,
+        elem_size: Option<usize>,
+        threads: usize,
+    ) -> f64 {
+        let (cost, slots, moved) = match (kind, elem_size) {
+            (SamplerKind::Naive, _) => (self.naive, n, k),
+            (SamplerKind::ParPriority, _) => (self.priority, n, k),
+            (SamplerKind::ParPermutation, None) => (self.permutation, n.min(2 * k), 0),
+            // copies the whole population, not only the sample
+            (SamplerKind::ParPermutation, Some(_)) => (self.permutation, n, n),
+            _ => return f64::INFINITY,
+        };
+        let elem_size = elem_size.unwrap_or(size_of::<usize>());
+        let extra_bytes = (elem_size as f64 - size_of::<usize>() as f64) * moved as f64;
+        let total = cost.estimate(slots as f64, k as f64, threads)
+            + self.copy.estimate(extra_bytes, 0.0, threads);
+        total.max(0.0)
+    }
+
+    /// The candidate with the lowest estimate.
+    pub fn choose(
+        &self,
+        n: usize,
+        k: usize,
+        elem_size: Option<usize>,
+        threads: usize,
+    ) -> SamplerKind {
+        if prefers_floyd(n, k) {
+            return SamplerKind::ParFloyd;
+        }
+        [
+            SamplerKind::Naive,
+            SamplerKind::ParPriority,
+            SamplerKind::ParPermutation,
+        ]
+        .into_iter()
+        .min_by(|&a, &b| {
+            self.estimate(a, n, k, elem_size, threads)
+                .total_cmp(&self.estimate(b, n, k, elem_size, threads))
+        })
+        .unwrap()
+    }
+
+    /// Writes the model as a CSV with one `algorithm,per_n,per_k,parallel`
+    /// row per cost.
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> csv::Result<()> {
+        let mut wtr = csv::Writer::from_path(path)?;
+        wtr.write_record(["algorithm", "per_n", "per_k", "parallel"])?;
+        [
+            ("Naive", self.naive),
+            ("ParPriority", self.priority),
+            ("ParPermutation", self.permutation),
+            ("Copy", self.copy),
+        ]
+        .iter()
+        .try_for_each(|(name, cost)| {
+            wtr.write_record([
+                name.to_string(),
+                cost.per_n.to_string(),
+                cost.per_k.to_string(),
+                cost.parallel.to_string(),
+            ])
+        })?;
+        wtr.flush()?;
+        Ok(())
+    }
+
+    /// Reads a file written by `save`. Costs missing from the file keep
+    /// their defaults.
+    pub fn load<P: AsRef<Path>>(path: P) -> csv::Result<Self> {
+        let mut model = Self::default();
+        for row in csv::Reader::from_path(path)?.deserialize() {
+            let (name, per_n, per_k, parallel): (String, f64, f64, f64) = row?;
+            let cost = Cost::new(per_n, per_k, parallel);
+            match name.as_str() {
+                "Naive" => model.naive = cost,
+                "ParPriority" => model.priority = cost,
+                "ParPermutation" => model.permutation = cost,
+                "Copy" => model.copy = cost,
+                _ => {}
+            }
+        }
+        Ok(model)
+    }
+}
+
+/// Fastest of `CALIBRATION_REPEATS` runs of `f`, in nanoseconds.
+fn time_ns<R>(f: impl Fn() -> R) -> f64 {
+    (0..CALIBRATION_REPEATS)
+        .map(|_| {
+            let start = Instant::now();
+            std::hint::black_box(f());
+            start.elapsed().as_nanos() as f64
+        })
+        .fold(f64::INFINITY, f64::min)
+}
+
+/// Fits a `CostModel` to this machine by timing every candidate on `n`
+/// `usize`s at 10% and 90% sampled, once on one thread and once on all
+/// threads of the current pool. Takes a few seconds for `n` around a
+/// million; persist the result with `CostModel::save`.
+pub fn calibrate(n: usize) -> Result<CostModel, SamplingError> {
+    let threads = rayon::current_num_threads();
+    let defaults = CostModel::default();
+    let configs = [
+        SamplerConfig::new().seed(0).num_threads(1)?,
+        SamplerConfig::new().seed(0).num_threads(threads)?,
+    ];
+    let ks = (n / 10, n - n / 10);
+    let data: Vec<usize> = (0..n).into_par_iter().collect();
+
+    let fit = |kind: SamplerKind, fallback: Cost| {
+        let [seq, par] = configs.clone().map(|config| {
+            let sampler: Box<dyn Sampler<usize>> = kind.build(config);
+            [ks.0, ks.1].map(|k| time_ns(|| sampler.sample(&data, k)))
+        });
+        Cost::fit(n, ks, seq, par, threads, fallback)
+    };
+
+    let [seq_copy, par_copy] = configs.clone().map(|config| {
+        config.install(|| time_ns(|| data.par_iter().copied().collect::<Vec<usize>>()))
+    });
+    let copy = Cost::fit(n, ks, [seq_copy; 2], [par_copy; 2], threads, defaults.copy);
+
+    Ok(CostModel {
+        naive: fit(SamplerKind::Naive, defaults.naive),
+        priority: fit(SamplerKind::ParPriority, defaults.priority),
+        permutation: fit(SamplerKind::ParPermutation, defaults.permutation),
+        copy: Cost::new(
+            seq_copy / (n * size_of::<usize>()) as f64,
+            0.0,
+            copy.parallel,
+        ),
+    })
+}
+
+/// Picks Naive, priority or permutation sampling for every call from `n`,
+/// `k`, the size of the elements and a thread count, using a `CostModel`.
+/// Tiny samples always go to `ParFloydSampler`.
+///
+/// The thread count is the machine's number of cores, or the one given to
+/// `plan_threads`, never that of the pool a call runs on: a seed gives the
+/// same sample on any number of threads. Machines with different core counts
+/// may still pick different algorithms, and so draw different samples, unless
+/// the count is pinned.
+#[derive(Clone, Default)]
+pub struct AutoSampler {
+    config: SamplerConfig,
+    model: CostModel,
+    threads: Option<usize>,
+}
+
+impl AutoSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Uses `model`, e.g. from `calibrate` or `CostModel::load`, instead of
+    /// the built-in one.
+    pub fn with_model(mut self, model: CostModel) -> Self {
+        self.model = model;
+        self
+    }
+
+    /// Plans for `threads` threads instead of the machine's core count.
+    pub fn plan_threads(mut self, threads: usize) -> Self {
+        self.threads = Some(threads);
+        self
+    }
+
+    fn planned_threads(&self) -> usize {
+        self.threads.unwrap_or_else(|| {
+            std::thread::available_parallelism().map_or(1, |threads| threads.get())
+        })
+    }
+
+    /// The algorithm a call with these sizes would run, with `elem_size`
+    /// `None` for index sampling.
+    pub fn choose(&self, n: usize, k: usize, elem_size: Option<usize>) -> SamplerKind {
+        self.model.choose(n, k, elem_size, self.planned_threads())
+    }
+
+    /// Indices from the algorithm chosen for elements of `elem_size` bytes,
+    /// so positions and values of one call come from the same algorithm.
+    fn sample_indices_sized(
+        &self,
+        n: usize,
+        k: usize,
+        elem_size: Option<usize>,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        match self.choose(n, k, elem_size) {
+            SamplerKind::Naive => NaiveSampler::new().sample_indices_in_pool(n, k, seed),
+            SamplerKind::ParPriority => {
+                ParPrioritySampler::new().sample_indices_in_pool(n, k, seed)
+            }
+            SamplerKind::ParPermutation => {
+                PermutationSampler::new().sample_indices_in_pool(n, k, seed)
+            }
+            _ => ParFloydSampler::new().sample_indices_in_pool(n, k, seed),
+        }
+    }
+}
+
+impl Configurable for AutoSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for AutoSampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_sized(n, k, None, seed)
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for AutoSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_sized(arr.len(), k, Some(size_of::<T>()), seed)
+    }
+
+    fn sample_in_pool(&self, arr: &[T], k: usize, seed: u64) -> Result<Vec<T>, SamplingError> {
+        let kind = self.choose(arr.len(), k, Some(size_of::<T>()));
+        kind.build(SamplerConfig::new())
+            .sample_in_pool(arr, k, seed)
+    }
+}
+
+mod test {
+    #[test]
+    fn auto_picks_cheapest() {
+        use super::{Cost, CostModel};
+        use crate::samplers::sampl_interface::SamplerKind;
+
+        let model = CostModel {
+            naive: Cost::new(10.0, 10.0, 0.0),
+            priority: Cost::new(20.0, 0.0, 1.0),
+            permutation: Cost::new(1.0, 20.0, 0.0),
+            copy: Cost::new(1.0, 0.0, 0.0),
+        };
+        let n = 1_000_000;
+        assert_eq!(SamplerKind::ParFloyd, model.choose(n, 10, Some(8), 1));
+        assert_eq!(
+            SamplerKind::ParPermutation,
+            model.choose(n, n / 10, Some(8), 1)
+        );
+        assert_eq!(
+            SamplerKind::ParPriority,
+            model.choose(n, n / 10, Some(8), 32)
+        );
+        // big elements make copying the whole population too expensive
+        assert_eq!(SamplerKind::Naive, model.choose(n, n / 10, Some(64), 1));
+
+        // indices alone never copy the population, so permuting gets cheaper
+        let model = CostModel::default();
+        assert_eq!(
+            SamplerKind::ParPriority,
+            model.choose(n, n / 50, Some(8), 64)
+        );
+        assert_eq!(
+            SamplerKind::ParPermutation,
+            model.choose(n, n / 50, None, 64)
+        );
+    }
+
+    #[test]
+    fn auto_positions_follow_element_size() {
+        use super::{AutoSampler, Cost, CostModel};
+        use crate::samplers::sampl_interface::{OutputOrder, Sampler, SamplerConfig};
+
+        // permutation for `usize`, but naive for elements of 64 bytes
+        let model = CostModel {
+            naive: Cost::new(10.0, 10.0, 0.0),
+            priority: Cost::new(20.0, 0.0, 1.0),
+            permutation: Cost::new(1.0, 20.0, 0.0),
+            copy: Cost::new(1.0, 0.0, 0.0),
+        };
+        let xs: Vec<[usize; 8]> = (0..100_000).map(|i| [i; 8]).collect();
+        let [mut any, stable] = [OutputOrder::Any, OutputOrder::Stable].map(|order| {
+            AutoSampler::new()
+                .with_model(model)
+                .plan_threads(1)
+                .with_config(SamplerConfig::new().seed(3).order(order))
+                .sample(&xs, 10_000)
+                .unwrap()
+        });
+        any.sort_unstable();
+        assert_eq!(any, stable);
+    }
+
+    #[test]
+    fn auto_seeded_across_threads() {
+        use super::AutoSampler;
+        use crate::samplers::sampl_interface::{Sampler, SamplerConfig};
+
+        // the default model picks differently for one thread and for 64
+        let xs: Vec<usize> = (0..1_000_000).collect();
+        let k = xs.len() / 50;
+        let samples = [1, 64].map(|threads| {
+            AutoSampler::new()
+                .with_config(SamplerConfig::new().seed(7).num_threads(threads).unwrap())
+                .sample(&xs, k)
+                .unwrap()
+        });
+        assert_eq!(samples[0], samples[1]);
+    }
+
+    #[test]
+    fn auto_model_round_trips() {
+        use super::{calibrate, AutoSampler, CostModel};
+        use crate::samplers::sampl_interface::{Sampler, SamplerConfig};
+
+        // tiny, as only the round trip is checked, not the fitted costs
+        let model = calibrate(1 << 10).unwrap();
+        let path = std::env::temp_dir().join(format!(
+            "parrd_sampling_auto_model_{}.csv",
+            std::process::id()
+        ));
+        model.save(&path).unwrap();
+        assert_eq!(model, CostModel::load(&path).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        let xs: Vec<u8> = (0..=255).collect();
+        let sampler = AutoSampler::new()
+            .with_model(model)
+            .with_config(SamplerConfig::new().seed(4));
+        assert_eq!(sampler.sample(&xs, 100), sampler.sample(&xs, 100));
+    }
+}
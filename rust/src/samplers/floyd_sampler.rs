@@ -0,0 +1,193 @@
+use std::collections::HashSet;
+
+use rand::{Rng, SeedableRng};
+use rayon::{
+    iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator},
+    slice::ParallelSliceMut,
+};
+
+use crate::samplers::{
+    sampl_error::SamplingError,
+    sampl_interface::{Configurable, IndexSampler, Sampler, SamplerConfig},
+};
+use crate::utils::{
+    reservations::MinTable,
+    rng::{index_rng, SplitMix64},
+};
+
+/// `NaiveSampler`, `AutoSampler` and the other O(n) samplers built with
+/// `floyd(true)` hand over to Floyd's algorithm once `k <= n / FLOYD_RATIO`.
+const FLOYD_RATIO: usize = 1 << 10;
+
+pub(crate) fn prefers_floyd(n: usize, k: usize) -> bool {
+    k <= n / FLOYD_RATIO
+}
+
+/// Robert Floyd's algorithm: one draw per sampled index and a set of only
+/// `k` entries, so it suits `k` much smaller than `n`.
+#[derive(Clone, Default)]
+pub struct FloydSampler {
+    config: SamplerConfig,
+}
+
+impl FloydSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+impl Configurable for FloydSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for FloydSampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        let mut rng = SplitMix64::seed_from_u64(seed);
+        let mut chosen = HashSet::with_capacity(k);
+        let mut ans = Vec::with_capacity(k);
+        (n - k..n).for_each(|j| {
+            let t = rng.gen_range(0..=j);
+            let pick = if chosen.contains(&t) { j } else { t };
+            chosen.insert(pick);
+            ans.push(pick);
+        });
+        Ok(ans)
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for FloydSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_in_pool(arr.len(), k, seed)
+    }
+}
+
+/// Batch version for many threads: draws candidates with replacement in
+/// parallel, deduplicates them in a lock-free set, and repeats until `k`
+/// distinct values turned up. The sample is the first `k` distinct values in
+/// draw order, which is a uniform sample in uniformly random order. Memory
+/// is O(k), but the expected number of draws grows like `n ln(n / (n - k))`,
+/// so it is meant for `k` much smaller than `n`.
+#[derive(Clone, Default)]
+pub struct ParFloydSampler {
+    config: SamplerConfig,
+}
+
+impl ParFloydSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+impl Configurable for ParFloydSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for ParFloydSampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        // at most 2k distinct values ever go in, keeping the set half empty
+        let set = MinTable::with_capacity(2 * k);
+        let (mut drawn, mut distinct) = (0, 0);
+        while distinct < k {
+            let expected = (k - distinct) as f64 * n as f64 / (n - distinct) as f64;
+            let batch = (expected as usize + expected as usize / 16 + 64).min(2 * k - distinct);
+            distinct += (drawn..drawn + batch)
+                .into_par_iter()
+                .filter(|&draw| set.insert_min(index_rng(seed, draw).gen_range(0..n), draw))
+                .count();
+            drawn += batch;
+        }
+
+        // ordered by the first draw of every value
+        let mut entries: Vec<(usize, usize)> = set
+            .entries()
+            .into_par_iter()
+            .map(|(value, first)| (first, value))
+            .collect();
+        entries.par_sort_unstable();
+        Ok(entries[..k].par_iter().map(|&(_, value)| value).collect())
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for ParFloydSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_in_pool(arr.len(), k, seed)
+    }
+}
+
+mod test {
+    #[test]
+    fn floyd_is_uniform() {
+        use super::{FloydSampler, ParFloydSampler};
+        use crate::samplers::sampl_interface::{
+            test::{assert_uniform, inclusion_counts},
+            IndexSampler,
+        };
+
+        let (n, k, trials) = (20, 5, 20_000);
+        let samplers: [Box<dyn IndexSampler>; 2] = [
+            Box::new(FloydSampler::new()),
+            Box::new(ParFloydSampler::new()),
+        ];
+        samplers.iter().for_each(|sampler| {
+            let counts = inclusion_counts(n, k, trials, |seed| {
+                sampler.sample_indices_seeded(n, k, seed).unwrap()
+            });
+            assert_uniform(&counts, (trials as usize * k / n) as f64, 0.05);
+        });
+    }
+
+    #[test]
+    fn par_floyd_distinct_and_seeded() {
+        use super::ParFloydSampler;
+        use crate::samplers::sampl_interface::{IndexSampler, SamplerConfig};
+        use std::collections::HashSet;
+
+        [(1_000_000_000, 100_000), (1_000, 1_000)]
+            .iter()
+            .for_each(|&(n, k)| {
+                let samples = [1, 4].map(|threads| {
+                    ParFloydSampler::new()
+                        .with_config(SamplerConfig::new().seed(21).num_threads(threads).unwrap())
+                        .sample_indices(n, k)
+                        .unwrap()
+                });
+                assert_eq!(samples[0], samples[1]);
+                assert_eq!(k, samples[0].iter().collect::<HashSet<_>>().len());
+                assert!(samples[0].iter().all(|&i| i < n));
+            });
+    }
+}
@@ -0,0 +1,206 @@
+use rand::Rng;
+use rand_distr::{Distribution, Hypergeometric};
+
+use crate::samplers::{
+    sampl_error::SamplingError,
+    sampl_interface::{Configurable, IndexSampler, Sampler, SamplerConfig},
+};
+use crate::utils::rng::{index_rng, SplitMix64};
+
+/// Below this many samples, both halves are handled on the current thread.
+const SEQ_CUTOFF: usize = 1 << 10;
+
+/// Ranges drawing at most this many indices are finished by Floyd's
+/// algorithm. Splitting them further would send `Hypergeometric` down its
+/// inversion path, whose setup loops over the whole range.
+const LEAF_SIZE: usize = 1 << 6;
+
+/// How many of `k` indices drawn without replacement from `0..n` fall in
+/// `0..left`.
+fn left_count(n: usize, left: usize, k: usize, rng: &mut SplitMix64) -> usize {
+    Hypergeometric::new(n as u64, left as u64, k as u64)
+        .unwrap()
+        .sample(rng) as usize
+}
+
+/// Fills `out` with `out.len()` increasing indices from `base..base + n`:
+/// splits the range in half, draws how many of the indices land in the left
+/// half, and recurses on both halves, in parallel for big enough samples.
+/// Ranges with at most `leaf_size` indices to draw go to `leaf` instead.
+/// `node` numbers the recursion tree like a heap, so every range draws from
+/// its own stream and the result does not depend on the thread count.
+pub(crate) fn split_sample<L>(
+    out: &mut [usize],
+    base: usize,
+    n: usize,
+    seed: u64,
+    node: usize,
+    leaf_size: usize,
+    leaf: &L,
+) where
+    L: Fn(&mut [usize], usize, usize, &mut SplitMix64) + Sync,
+{
+    let k = out.len();
+    if k == 0 {
+        return;
+    }
+    if k == n {
+        out.iter_mut()
+            .enumerate()
+            .for_each(|(i, slot)| *slot = base + i);
+        return;
+    }
+
+    let mut rng = index_rng(seed, node);
+    if k <= leaf_size {
+        leaf(out, base, n, &mut rng);
+        return;
+    }
+
+    let half = n / 2;
+    let (left, right) = out.split_at_mut(left_count(n, half, k, &mut rng));
+    let mut sample_left = || split_sample(left, base, half, seed, 2 * node, leaf_size, leaf);
+    let mut sample_right = || {
+        split_sample(
+            right,
+            base + half,
+            n - half,
+            seed,
+            2 * node + 1,
+            leaf_size,
+            leaf,
+        )
+    };
+    if k > SEQ_CUTOFF {
+        rayon::join(sample_left, sample_right);
+    } else {
+        sample_left();
+        sample_right();
+    }
+}
+
+/// `out.len()` increasing indices from `base..base + n` by Floyd's
+/// algorithm. Leaves are at most `LEAF_SIZE` long, so a linear scan finds
+/// repeats quickly.
+fn floyd_leaf(out: &mut [usize], base: usize, n: usize, rng: &mut SplitMix64) {
+    let k = out.len();
+    (n - k..n).enumerate().for_each(|(filled, j)| {
+        let t = rng.gen_range(0..=j);
+        out[filled] = if out[..filled].contains(&t) { j } else { t };
+    });
+    out.sort_unstable();
+    out.iter_mut().for_each(|i| *i += base);
+}
+
+/// Divide and conquer over the index range: split `0..n` in half, draw how
+/// many of the `k` samples land in each half (hypergeometric), and recurse
+/// on both halves with `rayon::join` until at most `LEAF_SIZE` samples are
+/// left, which Floyd's algorithm draws. Only the sample itself is written,
+/// so the work is O(k) plus O((k / LEAF_SIZE) log n) splits, with no O(n)
+/// pass, and the indices come out sorted.
+#[derive(Clone, Default)]
+pub struct HypergeometricSampler {
+    config: SamplerConfig,
+}
+
+impl HypergeometricSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+impl Configurable for HypergeometricSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for HypergeometricSampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        let mut indices = vec![0; k];
+        split_sample(&mut indices, 0, n, seed, 1, LEAF_SIZE, &floyd_leaf);
+        Ok(indices)
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for HypergeometricSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_in_pool(arr.len(), k, seed)
+    }
+}
+
+mod test {
+    #[test]
+    fn hypergeometric_is_uniform() {
+        use super::HypergeometricSampler;
+        use crate::samplers::sampl_interface::{
+            test::{assert_uniform, inclusion_counts},
+            IndexSampler,
+        };
+
+        // a single leaf, and enough samples to split a few times
+        let sampler = HypergeometricSampler::new();
+        [(20, 5, 20_000), (200, 100, 10_000)]
+            .iter()
+            .for_each(|&(n, k, trials)| {
+                let counts = inclusion_counts(n, k, trials, |seed| {
+                    let indices = sampler.sample_indices_seeded(n, k, seed).unwrap();
+                    assert!(indices.windows(2).all(|w| w[0] < w[1]));
+                    indices
+                });
+                assert_uniform(&counts, (trials as usize * k / n) as f64, 0.05);
+            });
+    }
+
+    #[test]
+    fn hypergeometric_big_and_seeded() {
+        use super::HypergeometricSampler;
+        use crate::samplers::sampl_interface::{IndexSampler, SamplerConfig};
+
+        let n = 10_000_000;
+        let samples = [1, 4].map(|threads| {
+            HypergeometricSampler::new()
+                .with_config(SamplerConfig::new().seed(13).num_threads(threads).unwrap())
+                .sample_indices(n, n / 2)
+                .unwrap()
+        });
+
+        assert_eq!(samples[0], samples[1]);
+        assert_eq!(n / 2, samples[0].len());
+        assert!(samples[0].windows(2).all(|w| w[0] < w[1]));
+        assert!(samples[0][n / 2 - 1] < n);
+    }
+
+    #[test]
+    fn hypergeometric_small_k_of_huge_n() {
+        use super::HypergeometricSampler;
+        use crate::samplers::sampl_interface::{IndexSampler, SamplerConfig};
+
+        // far too big to finish if any step were linear in n
+        let n = 1 << 50;
+        [1, 10, 1_000, 100_000].iter().for_each(|&k| {
+            let indices = HypergeometricSampler::new()
+                .with_config(SamplerConfig::new().seed(k as u64))
+                .sample_indices(n, k)
+                .unwrap();
+            assert_eq!(k, indices.len());
+            assert!(indices.windows(2).all(|w| w[0] < w[1]));
+            assert!(indices[k - 1] < n);
+        });
+    }
+}
@@ -1,4 +1,13 @@
+pub mod auto_sampler;
+pub mod floyd_sampler;
+pub mod hypergeometric_sampler;
 pub mod naive_sampler;
 pub mod perm_sampler;
+pub mod priority_sample;
 pub mod priority_sampler;
+pub mod replacement_sampler;
+pub mod reservoir_sampler;
+pub mod sampl_error;
 pub mod sampl_interface;
+pub mod selection_sampler;
+pub mod stratified_sampler;
@@ -1,28 +1,117 @@
-use std::{collections::HashSet, marker::PhantomData};
+use std::collections::HashSet;
 
-use rand::Rng;
+use rand::{Rng, SeedableRng};
 
-use super::sampl_interface::Sampler;
+use super::{
+    floyd_sampler::{prefers_floyd, FloydSampler},
+    sampl_error::SamplingError,
+    sampl_interface::{
+        complement_sample, complement_sample_indices, gather, Complement, Configurable,
+        IndexSampler, Sampler, SamplerConfig,
+    },
+};
+use crate::utils::rng::SplitMix64;
 
-pub struct NaiveSampler<T: Clone + Sized + Send + Sync> {
-    marker: PhantomData<T>,
+#[derive(Clone)]
+pub struct NaiveSampler {
+    config: SamplerConfig,
+    complement: bool,
+    floyd: bool,
 }
 
-impl<T: Clone + Sized + Send + Sync> Sampler<T> for NaiveSampler<T> {
-    fn sample(arr: &[T], k: usize) -> Option<Vec<T>> {
+impl Default for NaiveSampler {
+    fn default() -> Self {
+        Self {
+            config: SamplerConfig::default(),
+            complement: false,
+            floyd: true,
+        }
+    }
+}
+
+impl NaiveSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// With `floyd` on, the default, samples of at most `n / 1024` items go
+    /// to `FloydSampler` instead of hashing the whole population. There is
+    /// no sequential twin whose samples this must match, so it only gets
+    /// turned off to reproduce samples drawn without it.
+    pub fn floyd(mut self, floyd: bool) -> Self {
+        self.floyd = floyd;
+        self
+    }
+}
+
+impl Complement for NaiveSampler {
+    fn complement(mut self, complement: bool) -> Self {
+        self.complement = complement;
+        self
+    }
+
+    fn complements(&self) -> bool {
+        self.complement
+    }
+}
+
+impl Configurable for NaiveSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for NaiveSampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        if let Some(indices) = complement_sample_indices(self, n, k, seed) {
+            return indices;
+        }
+        if self.floyd && prefers_floyd(n, k) {
+            return FloydSampler::new().sample_indices_in_pool(n, k, seed);
+        }
+
         let mut ans = Vec::with_capacity(k);
-        let mut idx_left: HashSet<usize> = (0..arr.len()).collect();
-        let mut rng = rand::thread_rng();
+        let mut idx_left: HashSet<usize> = (0..n).collect();
+        let mut rng = SplitMix64::seed_from_u64(seed);
 
         while ans.len() < k {
-            let idx = rng.gen_range(0..arr.len());
+            let idx = rng.gen_range(0..n);
             if idx_left.contains(&idx) {
-                ans.push(arr[idx].clone());
+                ans.push(idx);
                 idx_left.remove(&idx);
             }
         }
 
-        Some(ans)
+        Ok(ans)
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for NaiveSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_in_pool(arr.len(), k, seed)
+    }
+
+    fn sample_in_pool(&self, arr: &[T], k: usize, seed: u64) -> Result<Vec<T>, SamplingError> {
+        let n = arr.len();
+        if let Some(values) = complement_sample(self, arr, k, seed) {
+            return values;
+        }
+        Ok(gather(arr, &self.sample_indices_in_pool(n, k, seed)?))
     }
 }
 
@@ -33,7 +122,7 @@ mod test {
         use crate::samplers::sampl_interface::Sampler;
 
         let xs = vec![3, 5, 1, 2, 3, 8, 6, 3];
-        let xs_sample = NaiveSampler::sample(&xs, 3);
+        let xs_sample = NaiveSampler::new().sample(&xs, 3);
         assert_eq!(xs_sample.unwrap().len(), 3);
     }
 
@@ -45,7 +134,31 @@ mod test {
         let n = 1_000_000;
         let k = 10_000;
         let xs: Vec<i32> = (0..n).collect();
-        let xs_sample = NaiveSampler::sample(&xs, k);
+        let xs_sample = NaiveSampler::new().sample(&xs, k);
         assert_eq!(xs_sample.unwrap().len(), k);
     }
+
+    #[test]
+    fn tiny_naive_sample_skips_hashing() {
+        use super::NaiveSampler;
+        use crate::samplers::sampl_interface::IndexSampler;
+        use std::collections::HashSet;
+
+        // hashing all of `n` would take minutes and gigabytes
+        let n = 1 << 40;
+        let indices = NaiveSampler::new().sample_indices(n, 10).unwrap();
+        assert_eq!(10, indices.iter().collect::<HashSet<_>>().len());
+        assert!(indices.iter().all(|&i| i < n));
+    }
+
+    #[test]
+    fn seeded_naive_sample() {
+        use super::NaiveSampler;
+        use crate::samplers::sampl_interface::Sampler;
+
+        let xs: Vec<i32> = (0..10_000).collect();
+        let first = NaiveSampler::new().sample_seeded(&xs, 100, 7);
+        let second = NaiveSampler::new().sample_seeded(&xs, 100, 7);
+        assert_eq!(first, second);
+    }
 }
@@ -1,35 +1,49 @@
 use rand::Rng;
-use rayon::iter::{
-    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
-};
-use std::{
-    marker::PhantomData,
-    sync::atomic::{AtomicUsize, Ordering as AtomicOrdering},
+use rayon::{
+    iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator},
+    slice::ParallelSliceMut,
 };
+use std::sync::atomic::{AtomicU32, AtomicUsize};
 
 use crate::{
-    samplers::sampl_interface::Sampler,
-    utils::{cwslice::UnsafeSlice, prefix_scan::par_scan},
+    samplers::{
+        floyd_sampler::{prefers_floyd, ParFloydSampler},
+        sampl_error::{check_k, SamplingError},
+        sampl_interface::{
+            complement_sample, complement_sample_indices, gather, Complement, Configurable,
+            IndexSampler, Sampler, SamplerConfig,
+        },
+    },
+    utils::{
+        cwslice::UnsafeSlice,
+        det_reservations::{speculative_for, ReserveCommit, RoundPolicy, RoundStats},
+        reservations::{DenseReservations, Reservations, SparseReservations},
+        rng::index_rng,
+    },
 };
 
-const PREFIX_DIVISOR: usize = 100;
+/// Reservations are kept sparse when `k <= n / SPARSE_RATIO`.
+const SPARSE_RATIO: usize = 4;
 
-fn generate_swaps(n: usize) -> Vec<usize> {
-    (0..n) // H in the J. Shun paper
+/// Swap targets of the first `k` steps of a Knuth shuffle of `n` items.
+fn generate_swaps(n: usize, k: usize, seed: u64) -> Vec<usize> {
+    (0..k) // H in the J. Shun paper
         .into_par_iter()
-        .map_init(rand::thread_rng, |rng, i| rng.gen_range(i..n))
+        .map(|i| index_rng(seed, i).gen_range(i..n))
         .collect::<Vec<usize>>()
 }
 
-#[allow(dead_code)]
-fn knuth_shuffle<T: Clone + Sized>(arr: &[T], k: usize, swap_targets: &[usize]) -> Vec<T> {
-    let mut ans = arr.to_vec();
+fn knuth_shuffle_in_place<T>(arr: &mut [T], k: usize, swap_targets: &[usize]) {
     swap_targets
         .iter()
         .take(k)
         .enumerate()
-        .for_each(|(i, &target)| ans.swap(i, target));
+        .for_each(|(i, &target)| arr.swap(i, target));
+}
 
+fn knuth_shuffle<T: Clone + Sized>(arr: &[T], k: usize, swap_targets: &[usize]) -> Vec<T> {
+    let mut ans = arr.to_vec();
+    knuth_shuffle_in_place(&mut ans, k, swap_targets);
     ans[..k].to_vec()
 }
 
@@ -37,118 +51,431 @@ fn par_permute_k<T: Clone + Sized + Send + Sync>(
     arr: &[T],
     k: usize,
     swap_targets: &[usize],
+    policy: &RoundPolicy,
 ) -> Vec<T> {
-    let n = arr.len();
-
-    let reservation: Vec<AtomicUsize> = (0..n)
-        .into_par_iter()
-        .map(|_| AtomicUsize::new(n))
-        .collect(); // init'd as -1 in paper but I can't see the point
-    let reserve = |i: usize| {
-        reservation[i].fetch_min(i, AtomicOrdering::Relaxed);
-        reservation[swap_targets[i]].fetch_min(i, AtomicOrdering::Relaxed);
-    };
-
     let mut ans = arr.to_vec();
-    let ans_slice = UnsafeSlice::new(&mut ans);
-    let commit = |i: usize| -> usize {
-        let swap_idx = swap_targets[i];
-        unsafe {
-            if reservation[i].load(AtomicOrdering::Relaxed) == i
-                && reservation[swap_idx].load(AtomicOrdering::Relaxed) == i
-            {
-                ans_slice.swap(i, swap_idx);
-                0
+    par_permute_k_in_place(&mut ans, k, swap_targets, policy);
+    ans[..k].to_vec()
+}
+
+/// `par_permute_k` of `0..n` without building `0..n`: only the first `k`
+/// slots and the swap targets past them are touched, so those are renumbered
+/// into at most `2k` slots and permuted there.
+fn par_permute_k_indices(k: usize, swap_targets: &[usize], policy: &RoundPolicy) -> Vec<usize> {
+    let mut far: Vec<usize> = swap_targets
+        .par_iter()
+        .copied()
+        .filter(|&target| target >= k)
+        .collect();
+    far.par_sort_unstable();
+    far.dedup();
+    let local_targets: Vec<usize> = swap_targets
+        .par_iter()
+        .map(|&target| {
+            if target < k {
+                target
             } else {
-                1
+                k + far.binary_search(&target).unwrap()
             }
-        }
+        })
+        .collect();
+
+    let mut slots: Vec<usize> = (0..k).into_par_iter().chain(far).collect();
+    par_permute_k_in_place(&mut slots, k, &local_targets, policy);
+    slots.truncate(k);
+    slots
+}
+
+/// Same result as `knuth_shuffle_in_place`, using deterministic reservations.
+/// Small samples only keep reservations for the slots touched in each round;
+/// otherwise there is one atomic per slot, 32-bit whenever the indices fit.
+fn par_permute_k_in_place<T: Send + Sync>(
+    ans: &mut [T],
+    k: usize,
+    swap_targets: &[usize],
+    policy: &RoundPolicy,
+) -> RoundStats {
+    let n = ans.len();
+    if k <= n / SPARSE_RATIO {
+        permute_rounds(ans, k, swap_targets, policy, SparseReservations::new())
+    } else if n < u32::MAX as usize {
+        let reservations = DenseReservations::<AtomicU32>::new(n);
+        permute_rounds(ans, k, swap_targets, policy, reservations)
+    } else {
+        let reservations = DenseReservations::<AtomicUsize>::new(n);
+        permute_rounds(ans, k, swap_targets, policy, reservations)
+    }
+}
+
+fn permute_rounds<T: Send + Sync, R: Reservations>(
+    ans: &mut [T],
+    k: usize,
+    swap_targets: &[usize],
+    policy: &RoundPolicy,
+    reservations: R,
+) -> RoundStats {
+    let mut steps = KnuthSteps {
+        ans: UnsafeSlice::new(ans),
+        swap_targets,
+        reservations,
     };
+    speculative_for(&mut steps, k, policy)
+}
 
-    let mut idx_remaining = (0..k).collect::<Vec<usize>>();
-    let mut prefix_size = (idx_remaining.len() / PREFIX_DIVISOR).max(PREFIX_DIVISOR);
-    // max btw PREFIX_DIVISOR so if prefix_size < PREFIX_DIVISOR then it =/> 0
+/// Step `i` of a Knuth shuffle swaps `ans[i]` with `ans[swap_targets[i]]`,
+/// so it reserves both slots.
+struct KnuthSteps<'a, T, R> {
+    ans: UnsafeSlice<'a, T>,
+    swap_targets: &'a [usize],
+    reservations: R,
+}
 
-    while !idx_remaining.is_empty() {
-        // do reserve and commit
-        idx_remaining.par_iter().take(prefix_size).for_each(|&idx| {
-            reserve(idx);
-        });
-        let fail_commits: Vec<usize> = idx_remaining
-            .par_iter()
-            .take(prefix_size)
-            .map(|&idx| commit(idx))
-            .collect();
-
-        // pack things together for next round
-        let (failed_count, pack_locs) = par_scan(&fail_commits);
-        let mut new_idx_remaining =
-            vec![0usize; idx_remaining.len() - (pack_locs.len() - failed_count)];
-        let new_idx_remaining_slice = UnsafeSlice::new(&mut new_idx_remaining);
-        idx_remaining
-            .par_iter()
-            .enumerate()
-            .take(prefix_size)
-            .for_each(|(i, &idx)| {
-                // reservation[idx].store(n, AtomicOrdering::Relaxed);
-                reservation[swap_targets[idx]].store(n, AtomicOrdering::Relaxed);
-                if fail_commits[i] == 1 {
-                    unsafe {
-                        new_idx_remaining_slice.write(pack_locs[i], idx);
-                    }
-                }
-            });
-        if prefix_size <= idx_remaining.len() {
-            idx_remaining[prefix_size..]
-                .par_iter()
-                .enumerate()
-                .for_each(|(i, &idx)| {
-                    reservation[swap_targets[idx]].store(n, AtomicOrdering::Relaxed);
-                    unsafe {
-                        new_idx_remaining_slice.write(failed_count + i, idx);
-                    }
-                });
+impl<T: Send + Sync, R: Reservations> ReserveCommit for KnuthSteps<'_, T, R> {
+    fn start_round(&mut self, round_len: usize) {
+        self.reservations.start_round(round_len);
+    }
+
+    fn reserve(&self, i: usize) {
+        self.reservations.reserve(i, i);
+        self.reservations.reserve(self.swap_targets[i], i);
+    }
+
+    fn commit(&self, i: usize) -> bool {
+        let target = self.swap_targets[i];
+        let won = self.reservations.holds(i, i) && self.reservations.holds(target, i);
+        if won {
+            unsafe {
+                self.ans.swap(i, target);
+            }
         }
+        won
+    }
 
-        idx_remaining = new_idx_remaining;
-        prefix_size = (idx_remaining.len() / PREFIX_DIVISOR).max(PREFIX_DIVISOR);
+    fn finish_round(&mut self, round: &[usize]) {
+        let touched: Vec<usize> = if self.reservations.needs_touched() {
+            round
+                .par_iter()
+                .flat_map_iter(|&i| [i, self.swap_targets[i]])
+                .collect()
+        } else {
+            vec![]
+        };
+        self.reservations.finish_round(&touched);
     }
+}
 
-    ans[..k].to_vec()
+/// Uniformly shuffles `arr` in parallel with Shun et al.'s deterministic
+/// reservations; the result only depends on `seed`, not on the thread count.
+pub fn par_shuffle_seeded<T: Send + Sync>(arr: &mut [T], seed: u64) {
+    let swap_targets = generate_swaps(arr.len(), arr.len(), seed);
+    par_permute_k_in_place(arr, arr.len(), &swap_targets, &RoundPolicy::default());
 }
 
-pub struct SeqPermutationSampler<T: Clone + Sized> {
-    marker: PhantomData<T>,
+pub fn par_shuffle<T: Send + Sync>(arr: &mut [T]) {
+    par_shuffle_seeded(arr, rand::random());
 }
 
-impl<T: Clone + Sized> Sampler<T> for SeqPermutationSampler<T> {
-    fn sample(arr: &[T], k: usize) -> Option<Vec<T>> {
-        let swap_targets = generate_swaps(arr.len());
-        Some(knuth_shuffle(arr, k, &swap_targets))
+/// A uniformly random permutation of `0..n`.
+pub fn par_random_permutation_seeded(n: usize, seed: u64) -> Vec<usize> {
+    let mut perm: Vec<usize> = (0..n).into_par_iter().collect();
+    par_shuffle_seeded(&mut perm, seed);
+    perm
+}
+
+pub fn par_random_permutation(n: usize) -> Vec<usize> {
+    par_random_permutation_seeded(n, rand::random())
+}
+
+/// The first `k` items of `arr` after the first `steps` steps of its
+/// shuffle, and what every round of reservations did on the way.
+fn par_permute_with_stats<T: Clone + Send + Sync>(
+    arr: &[T],
+    steps: usize,
+    k: usize,
+    seed: u64,
+    policy: &RoundPolicy,
+) -> (Vec<T>, RoundStats) {
+    let swap_targets = generate_swaps(arr.len(), steps, seed);
+    let mut ans = arr.to_vec();
+    let stats = par_permute_k_in_place(&mut ans, steps, &swap_targets, policy);
+    ans.truncate(k);
+    (ans, stats)
+}
+
+/// Tuning of the samplers that permute with deterministic reservations.
+pub trait ReservationRounds: Sized {
+    /// How many indices every round of reservations takes on. Only changes
+    /// the speed, never the sample.
+    fn round_policy(self, round_policy: RoundPolicy) -> Self;
+
+    /// Each round processes `max(remaining / divisor, divisor)` indices.
+    fn prefix_divisor(self, prefix_divisor: usize) -> Self {
+        self.round_policy(RoundPolicy::Fraction(prefix_divisor))
     }
 }
 
-pub struct FullPermutationSampler<T: Clone + Sized + Send + Sync> {
-    marker: PhantomData<T>,
+#[derive(Clone, Default)]
+pub struct SeqPermutationSampler {
+    config: SamplerConfig,
+}
+
+impl SeqPermutationSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Permutes `arr` so that its first `k` slots hold the sample and returns
+    /// them; the rest of `arr` holds the elements left out. Same sample as
+    /// `sample` for the same seed, without copying the population.
+    pub fn sample_in_place<'a, T: Send + Sync>(
+        &self,
+        arr: &'a mut [T],
+        k: usize,
+    ) -> Result<&'a mut [T], SamplingError> {
+        check_k(arr.len(), k)?;
+        let swap_targets = generate_swaps(arr.len(), k, self.config.resolve_seed());
+        knuth_shuffle_in_place(arr, k, &swap_targets);
+        Ok(&mut arr[..k])
+    }
+}
+
+impl Configurable for SeqPermutationSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for SeqPermutationSampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        let swap_targets = generate_swaps(n, k, seed);
+        let indices: Vec<usize> = (0..n).collect();
+        Ok(knuth_shuffle(&indices, k, &swap_targets))
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for SeqPermutationSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_in_pool(arr.len(), k, seed)
+    }
+
+    fn sample_in_pool(&self, arr: &[T], k: usize, seed: u64) -> Result<Vec<T>, SamplingError> {
+        let swap_targets = generate_swaps(arr.len(), k, seed);
+        Ok(knuth_shuffle(arr, k, &swap_targets))
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct FullPermutationSampler {
+    config: SamplerConfig,
+    round_policy: RoundPolicy,
+}
+
+impl FullPermutationSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Like `sample`, but also reports what every round of reservations did,
+    /// for tuning the `RoundPolicy`. Ignores the configured order.
+    pub fn sample_with_stats<T: Clone + Send + Sync>(
+        &self,
+        arr: &[T],
+        k: usize,
+    ) -> Result<(Vec<T>, RoundStats), SamplingError> {
+        check_k(arr.len(), k)?;
+        let seed = self.config.resolve_seed();
+        Ok(self
+            .config
+            .install(|| par_permute_with_stats(arr, arr.len(), k, seed, &self.round_policy)))
+    }
+}
+
+impl ReservationRounds for FullPermutationSampler {
+    fn round_policy(mut self, round_policy: RoundPolicy) -> Self {
+        self.round_policy = round_policy;
+        self
+    }
+}
+
+impl Configurable for FullPermutationSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for FullPermutationSampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        let swap_targets = generate_swaps(n, n, seed);
+        let indices: Vec<usize> = (0..n).into_par_iter().collect();
+        Ok(par_permute_k(&indices, n, &swap_targets, &self.round_policy)[..k].to_vec())
+    }
 }
 
-impl<T: Clone + Sized + Send + Sync> Sampler<T> for FullPermutationSampler<T> {
-    fn sample(arr: &[T], k: usize) -> Option<Vec<T>> {
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for FullPermutationSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_in_pool(arr.len(), k, seed)
+    }
+
+    fn sample_in_pool(&self, arr: &[T], k: usize, seed: u64) -> Result<Vec<T>, SamplingError> {
         let n = arr.len();
-        let swap_targets = generate_swaps(n);
-        Some(par_permute_k(arr, n, &swap_targets)[..k].to_vec())
+        let swap_targets = generate_swaps(n, n, seed);
+        Ok(par_permute_k(arr, n, &swap_targets, &self.round_policy)[..k].to_vec())
     }
 }
 
-pub struct PermutationSampler<T: Clone + Sized + Send + Sync> {
-    marker: PhantomData<T>,
+#[derive(Clone, Default)]
+pub struct PermutationSampler {
+    config: SamplerConfig,
+    round_policy: RoundPolicy,
+    complement: bool,
+    floyd: bool,
 }
 
-impl<T: Clone + Sized + Send + Sync> Sampler<T> for PermutationSampler<T> {
-    fn sample(arr: &[T], k: usize) -> Option<Vec<T>> {
+impl PermutationSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Parallel version of `SeqPermutationSampler::sample_in_place`.
+    pub fn sample_in_place<'a, T: Send + Sync>(
+        &self,
+        arr: &'a mut [T],
+        k: usize,
+    ) -> Result<&'a mut [T], SamplingError> {
+        check_k(arr.len(), k)?;
+        let seed = self.config.resolve_seed();
+        self.config.install(|| {
+            let swap_targets = generate_swaps(arr.len(), k, seed);
+            par_permute_k_in_place(arr, k, &swap_targets, &self.round_policy);
+        });
+        Ok(&mut arr[..k])
+    }
+
+    /// Like `sample`, but also reports what every round of reservations did,
+    /// for tuning the `RoundPolicy`. Always runs the reservations, even where
+    /// `sample` would take a shortcut, and ignores the configured order.
+    pub fn sample_with_stats<T: Clone + Send + Sync>(
+        &self,
+        arr: &[T],
+        k: usize,
+    ) -> Result<(Vec<T>, RoundStats), SamplingError> {
+        check_k(arr.len(), k)?;
+        let seed = self.config.resolve_seed();
+        Ok(self
+            .config
+            .install(|| par_permute_with_stats(arr, k, k, seed, &self.round_policy)))
+    }
+
+    /// With `floyd` on, `sample` hands samples of at most `n / 1024` items
+    /// to `ParFloydSampler` rather than permuting a copy of the population.
+    /// The sample then differs from `SeqPermutationSampler`'s, and from
+    /// `sample_in_place` and `sample_with_stats`, which always permute, so
+    /// it is off by default.
+    pub fn floyd(mut self, floyd: bool) -> Self {
+        self.floyd = floyd;
+        self
+    }
+}
+
+impl ReservationRounds for PermutationSampler {
+    fn round_policy(mut self, round_policy: RoundPolicy) -> Self {
+        self.round_policy = round_policy;
+        self
+    }
+}
+
+impl Complement for PermutationSampler {
+    fn complement(mut self, complement: bool) -> Self {
+        self.complement = complement;
+        self
+    }
+
+    fn complements(&self) -> bool {
+        self.complement
+    }
+}
+
+impl Configurable for PermutationSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for PermutationSampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        if let Some(indices) = complement_sample_indices(self, n, k, seed) {
+            return indices;
+        }
+        if self.floyd && prefers_floyd(n, k) {
+            return ParFloydSampler::new().sample_indices_in_pool(n, k, seed);
+        }
+
+        let swap_targets = generate_swaps(n, k, seed);
+        Ok(par_permute_k_indices(k, &swap_targets, &self.round_policy))
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for PermutationSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_in_pool(arr.len(), k, seed)
+    }
+
+    fn sample_in_pool(&self, arr: &[T], k: usize, seed: u64) -> Result<Vec<T>, SamplingError> {
         let n = arr.len();
-        let swap_targets = generate_swaps(n);
-        Some(par_permute_k(arr, k, &swap_targets))
+        if let Some(values) = complement_sample(self, arr, k, seed) {
+            return values;
+        }
+        if self.floyd && prefers_floyd(n, k) {
+            return Ok(gather(arr, &self.sample_indices_in_pool(n, k, seed)?));
+        }
+
+        let swap_targets = generate_swaps(n, k, seed);
+        Ok(par_permute_k(arr, k, &swap_targets, &self.round_policy))
     }
 }
 
@@ -163,11 +490,27 @@ mod test {
         let xs: Vec<usize> = (0..n).collect();
 
         let seq_result = super::knuth_shuffle(&xs, k, &swap_targets);
-        let par_result = super::par_permute_k(&xs, k, &swap_targets);
+        let par_result =
+            super::par_permute_k(&xs, k, &swap_targets, &super::RoundPolicy::default());
 
         assert_eq!(&seq_result, &par_result);
     }
 
+    #[test]
+    fn perm_indices_match_full_permutation() {
+        use super::{generate_swaps, knuth_shuffle, par_permute_k_indices, RoundPolicy};
+
+        let n = 1_000_000;
+        let xs: Vec<usize> = (0..n).collect();
+        [1, 1_000, 400_000, n].iter().for_each(|&k| {
+            let swap_targets = generate_swaps(n, k, 9);
+            assert_eq!(
+                knuth_shuffle(&xs, k, &swap_targets),
+                par_permute_k_indices(k, &swap_targets, &RoundPolicy::default())
+            );
+        });
+    }
+
     #[test]
     fn perm_par_is_seq_small_early() {
         seq_par_perm_eq_test(20, 10);
@@ -190,4 +533,181 @@ mod test {
         let n = 10_000_000;
         seq_par_perm_eq_test(n, n);
     }
+
+    #[test]
+    fn perm_tiny_sample_is_seq() {
+        use super::{PermutationSampler, SeqPermutationSampler};
+        use crate::samplers::sampl_interface::{Sampler, SamplerConfig};
+
+        // small enough for Floyd's algorithm, which only runs when asked to
+        let xs: Vec<usize> = (0..1 << 20).collect();
+        let k = 100;
+        let config = SamplerConfig::new().seed(42);
+        let sampler = PermutationSampler::new().with_config(config.clone());
+        let sample = sampler.sample(&xs, k).unwrap();
+        assert_eq!(
+            SeqPermutationSampler::new()
+                .with_config(config)
+                .sample(&xs, k)
+                .unwrap(),
+            sample
+        );
+        assert_eq!(sample, sampler.sample_with_stats(&xs, k).unwrap().0);
+        let mut in_place = xs.clone();
+        assert_eq!(sample, sampler.sample_in_place(&mut in_place, k).unwrap());
+    }
+
+    #[test]
+    fn perm_seeded_across_threads() {
+        use super::{PermutationSampler, SeqPermutationSampler};
+        use crate::samplers::sampl_interface::Sampler;
+
+        let xs: Vec<usize> = (0..100_000).collect();
+        let k = 10_000;
+        let seq_result = SeqPermutationSampler::new().sample_seeded(&xs, k, 42);
+        [1, 4].iter().for_each(|&threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let par_result = pool.install(|| PermutationSampler::new().sample_seeded(&xs, k, 42));
+            assert_eq!(seq_result, par_result);
+        });
+    }
+
+    #[test]
+    fn perm_prefix_divisor_keeps_result() {
+        use super::{PermutationSampler, ReservationRounds};
+        use crate::samplers::sampl_interface::{Sampler, SamplerConfig};
+
+        let xs: Vec<usize> = (0..100_000).collect();
+        let config = SamplerConfig::new().seed(9);
+        let default_result = PermutationSampler::new()
+            .with_config(config.clone())
+            .sample(&xs, 5_000);
+        let tuned_result = PermutationSampler::new()
+            .with_config(config)
+            .prefix_divisor(7)
+            .sample(&xs, 5_000);
+        assert_eq!(default_result, tuned_result);
+    }
+
+    #[test]
+    fn perm_round_policies_keep_result() {
+        use super::{FullPermutationSampler, PermutationSampler, ReservationRounds};
+        use crate::samplers::sampl_interface::{Sampler, SamplerConfig};
+        use crate::utils::det_reservations::RoundPolicy;
+
+        let (n, k) = (100_000, 50_000);
+        let xs: Vec<usize> = (0..n).collect();
+        let config = SamplerConfig::new().seed(10);
+        let expected = PermutationSampler::new()
+            .with_config(config.clone())
+            .sample(&xs, k)
+            .unwrap();
+        let full_expected = FullPermutationSampler::new()
+            .with_config(config.clone())
+            .sample(&xs, k)
+            .unwrap();
+        [
+            RoundPolicy::Fixed(1_000),
+            RoundPolicy::Fraction(10),
+            RoundPolicy::Geometric {
+                initial: 16,
+                max_conflicts: 0.1,
+            },
+            RoundPolicy::Adaptive {
+                initial: 16,
+                target: 0.2,
+            },
+        ]
+        .iter()
+        .for_each(|&policy| {
+            let (sample, stats) = PermutationSampler::new()
+                .with_config(config.clone())
+                .round_policy(policy)
+                .sample_with_stats(&xs, k)
+                .unwrap();
+            assert_eq!(expected, sample, "{:?}", policy);
+            assert_eq!(stats.rounds(), stats.conflicts().len());
+            let taken: usize = stats.prefix_sizes().iter().sum();
+            let failed: usize = stats.conflicts().iter().sum();
+            assert_eq!(k, taken - failed, "{:?}", policy);
+
+            // the full permutation runs all `n` steps, whatever `k` is
+            let (sample, stats) = FullPermutationSampler::new()
+                .with_config(config.clone())
+                .round_policy(policy)
+                .sample_with_stats(&xs, k)
+                .unwrap();
+            assert_eq!(full_expected, sample, "{:?}", policy);
+            let taken: usize = stats.prefix_sizes().iter().sum();
+            let failed: usize = stats.conflicts().iter().sum();
+            assert_eq!(n, taken - failed, "{:?}", policy);
+        });
+    }
+
+    #[test]
+    fn perm_in_place_is_sample() {
+        use super::{PermutationSampler, SeqPermutationSampler};
+        use crate::samplers::sampl_interface::{Sampler, SamplerConfig};
+
+        let xs: Vec<usize> = (0..100_000).collect();
+        let k = 10_000;
+        let config = SamplerConfig::new().seed(21);
+
+        let seq_sampler = SeqPermutationSampler::new().with_config(config.clone());
+        let mut seq_xs = xs.clone();
+        let seq_in_place = seq_sampler.sample_in_place(&mut seq_xs, k).unwrap();
+        assert_eq!(seq_sampler.sample(&xs, k).unwrap(), seq_in_place);
+
+        let par_sampler = PermutationSampler::new().with_config(config);
+        let mut par_xs = xs.clone();
+        let par_in_place = par_sampler.sample_in_place(&mut par_xs, k).unwrap();
+        assert_eq!(seq_in_place, par_in_place);
+
+        par_xs.sort_unstable();
+        assert_eq!(xs, par_xs);
+    }
+
+    #[test]
+    fn perm_in_place_without_clone() {
+        use super::PermutationSampler;
+        use crate::samplers::sampl_error::SamplingError;
+
+        struct Record {
+            id: usize,
+        }
+
+        let mut records: Vec<Record> = (0..1_000).map(|id| Record { id }).collect();
+        let sampler = PermutationSampler::new();
+        let samples = sampler.sample_in_place(&mut records, 10).unwrap();
+        assert_eq!(10, samples.len());
+        assert!(samples.iter().all(|r| r.id < 1_000));
+        assert_eq!(
+            sampler.sample_in_place(&mut records, 1_001).err(),
+            Some(SamplingError::KGreaterThanN { k: 1_001, n: 1_000 })
+        );
+    }
+
+    #[test]
+    fn par_shuffle_matches_knuth() {
+        use super::{par_random_permutation_seeded, par_shuffle_seeded};
+        use crate::samplers::{perm_sampler::SeqPermutationSampler, sampl_interface::Sampler};
+
+        let n = 100_000;
+        let xs: Vec<usize> = (0..n).collect();
+        let mut shuffled = xs.clone();
+        par_shuffle_seeded(&mut shuffled, 4);
+        assert_eq!(
+            SeqPermutationSampler::new()
+                .sample_seeded(&xs, n, 4)
+                .unwrap(),
+            shuffled
+        );
+        assert_eq!(shuffled, par_random_permutation_seeded(n, 4));
+
+        shuffled.sort_unstable();
+        assert_eq!(xs, shuffled);
+    }
 }
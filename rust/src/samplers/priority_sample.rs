@@ -0,0 +1,191 @@
+use serde::{Deserialize, Serialize};
+
+/// The `k` items with the smallest priorities seen so far, together with those
+/// priorities. Since "k smallest of the union" equals "k smallest of the
+/// per-shard k smallest", sketches of disjoint shards merge into an exact
+/// uniform sample of the whole, as long as no two items share a priority
+/// stream (i.e. shards use their global offsets, or their own seeds).
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(try_from = "RawPrioritySample<T>")]
+pub struct PrioritySample<T> {
+    k: usize,
+    /// Sorted by priority, at most `k` long.
+    entries: Vec<(u64, T)>,
+}
+
+/// A `PrioritySample` as read, before its invariants are checked.
+#[derive(Deserialize)]
+struct RawPrioritySample<T> {
+    k: usize,
+    entries: Vec<(u64, T)>,
+}
+
+impl<T> TryFrom<RawPrioritySample<T>> for PrioritySample<T> {
+    type Error = String;
+
+    fn try_from(raw: RawPrioritySample<T>) -> Result<Self, Self::Error> {
+        if raw.entries.len() > raw.k {
+            return Err(format!(
+                "{} entries in a sample of at most {}",
+                raw.entries.len(),
+                raw.k
+            ));
+        }
+        if raw.entries.windows(2).any(|w| w[0].0 > w[1].0) {
+            return Err("entries are not sorted by priority".to_string());
+        }
+        Ok(Self {
+            k: raw.k,
+            entries: raw.entries,
+        })
+    }
+}
+
+impl<T> PrioritySample<T> {
+    /// `entries` must already be sorted by priority.
+    pub(crate) fn from_sorted(k: usize, mut entries: Vec<(u64, T)>) -> Self {
+        entries.truncate(k);
+        Self { k, entries }
+    }
+
+    pub fn k(&self) -> usize {
+        self.k
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// `(priority, item)` pairs in increasing priority.
+    pub fn entries(&self) -> &[(u64, T)] {
+        &self.entries
+    }
+
+    /// The largest priority kept, if any.
+    pub fn threshold(&self) -> Option<u64> {
+        self.entries.last().map(|&(priority, _)| priority)
+    }
+
+    pub fn items(&self) -> impl Iterator<Item = &T> {
+        self.entries.iter().map(|(_, x)| x)
+    }
+
+    pub fn into_items(self) -> Vec<T> {
+        self.entries.into_iter().map(|(_, x)| x).collect()
+    }
+
+    /// Keeps the smallest `min(a.k, b.k)` priorities of both. Equal priorities
+    /// favour `a`.
+    pub fn merge(a: Self, b: Self) -> Self {
+        let k = a.k.min(b.k);
+        let mut entries = Vec::with_capacity(k.min(a.len() + b.len()));
+        let mut a_iter = a.entries.into_iter().peekable();
+        let mut b_iter = b.entries.into_iter().peekable();
+
+        while entries.len() < k {
+            let take_a = match (a_iter.peek(), b_iter.peek()) {
+                (Some((pa, _)), Some((pb, _))) => pa <= pb,
+                (Some(_), None) => true,
+                (None, Some(_)) => false,
+                (None, None) => break,
+            };
+            let next = if take_a { a_iter.next() } else { b_iter.next() };
+            entries.extend(next);
+        }
+
+        Self { k, entries }
+    }
+
+    /// `None` if `samples` is empty.
+    pub fn merge_all<I: IntoIterator<Item = Self>>(samples: I) -> Option<Self> {
+        samples.into_iter().reduce(Self::merge)
+    }
+}
+
+mod test {
+    #[test]
+    fn merge_keeps_smallest() {
+        use super::PrioritySample;
+
+        let a = PrioritySample::from_sorted(3, vec![(1, 'a'), (4, 'b'), (9, 'c')]);
+        let b = PrioritySample::from_sorted(3, vec![(2, 'x'), (3, 'y'), (10, 'z')]);
+        let merged = PrioritySample::merge(a, b);
+
+        assert_eq!(&[(1, 'a'), (2, 'x'), (3, 'y')], merged.entries());
+        assert_eq!(Some(3), merged.threshold());
+    }
+
+    #[test]
+    fn shard_sketches_merge_uniformly() {
+        use super::PrioritySample;
+        use crate::samplers::{
+            priority_sampler::ParPrioritySampler,
+            sampl_interface::{
+                test::{assert_uniform, inclusion_counts},
+                SamplerConfig,
+            },
+        };
+
+        // every shard shares the seed, as workers of one job would
+        let population: Vec<usize> = (0..20).collect();
+        let (k, trials) = (5, 10_000u64);
+        let counts = inclusion_counts(population.len(), k, trials, |trial| {
+            let sampler = ParPrioritySampler::new().with_config(SamplerConfig::new().seed(trial));
+            let sketches = population
+                .chunks(5)
+                .enumerate()
+                .map(|(shard, chunk)| sampler.sketch(chunk, k, shard * 5));
+            let merged = PrioritySample::merge_all(sketches).unwrap();
+            merged.items().copied().collect()
+        });
+        assert_uniform(
+            &counts,
+            (trials as usize * k / population.len()) as f64,
+            0.05,
+        );
+    }
+
+    #[test]
+    fn sketch_roundtrips_through_json() {
+        use super::PrioritySample;
+        use crate::samplers::priority_sampler::ParPrioritySampler;
+
+        let population: Vec<String> = (0..1_000).map(|i| i.to_string()).collect();
+        let sketch = ParPrioritySampler::new().sketch(&population, 10, 0);
+        let json = serde_json::to_string(&sketch).unwrap();
+        let restored: PrioritySample<String> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(sketch, restored);
+    }
+
+    #[test]
+    fn sketch_rejects_broken_json() {
+        use super::PrioritySample;
+
+        let too_long = r#"{"k":1,"entries":[[1,"a"],[2,"b"]]}"#;
+        let unsorted = r#"{"k":2,"entries":[[2,"a"],[1,"b"]]}"#;
+        assert!(serde_json::from_str::<PrioritySample<String>>(too_long).is_err());
+        assert!(serde_json::from_str::<PrioritySample<String>>(unsorted).is_err());
+    }
+
+    #[test]
+    fn shard_sketches_match_whole_sketch() {
+        use super::PrioritySample;
+        use crate::samplers::{
+            priority_sampler::ParPrioritySampler, sampl_interface::SamplerConfig,
+        };
+
+        let population: Vec<usize> = (0..10_000).collect();
+        let sampler = ParPrioritySampler::new().with_config(SamplerConfig::new().seed(6));
+        let sketches = population
+            .chunks(3_000)
+            .enumerate()
+            .map(|(shard, chunk)| sampler.sketch(chunk, 50, shard * 3_000));
+        let merged = PrioritySample::merge_all(sketches).unwrap();
+        assert_eq!(sampler.sketch(&population, 50, 0), merged);
+    }
+}
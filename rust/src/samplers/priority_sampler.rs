@@ -1,155 +1,457 @@
-use rand::{rngs::ThreadRng, Rng};
-use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+use rand::{Rng, RngCore, SeedableRng};
+use rayon::{
+    iter::{
+        IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+    },
+    slice::ParallelSliceMut,
+};
 
-use crate::samplers::sampl_interface::Sampler;
-use crate::utils::{cwslice::UnsafeSlice, prefix_scan::par_scan};
+use crate::samplers::{
+    floyd_sampler::{prefers_floyd, ParFloydSampler},
+    priority_sample::PrioritySample,
+    sampl_error::{check_k, SamplingError},
+    sampl_interface::{
+        arrange_indices, complement_sample, complement_sample_indices, gather, Complement,
+        Configurable, IndexSampler, Sampler, SamplerConfig,
+    },
+};
+use crate::utils::{
+    pack::par_pack,
+    rng::{index_rng, open_unit, SplitMix64},
+    select::par_kth_smallest,
+};
 use core::hash::Hash;
-use std::{cmp::Ordering, marker::PhantomData, usize};
+use std::cmp::Ordering;
 
-fn quick_select<T: Clone + Hash + Sized>(xs: &[T], k: usize, rng: &mut ThreadRng) -> T {
+fn quick_select<T: Clone + Sized, K: Ord, F: Fn(&T) -> K>(
+    xs: &[T],
+    k: usize,
+    key: &F,
+    rng: &mut SplitMix64,
+) -> T {
     let n = xs.len();
     let pivot_idx = rng.gen_range(0..n);
-    let pivot_hash = fxhash::hash64(&xs[pivot_idx]);
+    let pivot_key = key(&xs[pivot_idx]);
 
-    let leq_elements: Vec<T> = xs
-        .iter()
-        .filter(|x| fxhash::hash64(x) <= pivot_hash)
-        .cloned()
-        .collect();
+    let leq_elements: Vec<T> = xs.iter().filter(|x| key(x) <= pivot_key).cloned().collect();
 
     match leq_elements.len().cmp(&k) {
         Ordering::Equal => xs[pivot_idx].clone(),
-        Ordering::Greater => quick_select(&leq_elements, k, rng),
+        Ordering::Greater => quick_select(&leq_elements, k, key, rng),
         _ => {
-            let gt_elements: Vec<T> = xs
-                .iter()
-                .filter(|x| fxhash::hash64(&x) > pivot_hash)
-                .cloned()
-                .collect();
-            quick_select(&gt_elements, k - leq_elements.len(), rng)
+            let gt_elements: Vec<T> = xs.iter().filter(|x| key(x) > pivot_key).cloned().collect();
+            quick_select(&gt_elements, k - leq_elements.len(), key, rng)
         }
     }
 }
 
-fn par_quick_select<T: Clone + Hash + Sized + Send + Sync>(
-    xs: &[T],
-    k: usize,
-    rng: &mut ThreadRng,
-) -> T {
-    let n = xs.len();
-    let pivot_idx = rng.gen_range(0..n);
-    let pivot_hash = fxhash::hash64(&xs[pivot_idx]);
+/// Indices of the `k` smallest keys, in increasing index order. Needs
+/// `0 < k <= keys.len()` and distinct keys, which is why every priority gets
+/// paired with its index: ties then break by position and exactly `k` indices
+/// come back.
+fn smallest_k(keys: &[(u64, usize)], k: usize, rng: &mut SplitMix64) -> Vec<usize> {
+    let kth_key = quick_select(keys, k, &|key: &(u64, usize)| *key, rng);
+    keys.iter()
+        .filter(|&&key| key <= kth_key)
+        .map(|&(_, i)| i)
+        .collect()
+}
 
-    let leq_flags: Vec<usize> = xs
-        .par_iter()
-        .map(|x: &T| match fxhash::hash64(&x).cmp(&pivot_hash) {
-            Ordering::Less | Ordering::Equal => 1,
-            _ => 0,
-        })
-        .collect();
-    let (leq_count, left_locs) = par_scan(&leq_flags);
+fn par_smallest_k(keys: &[(u64, usize)], k: usize) -> Vec<usize> {
+    let kth_key = *par_kth_smallest(keys, k - 1, |&key| key);
+    par_pack(keys, |&(priority, idx)| {
+        ((priority, idx) <= kth_key).then_some(idx)
+    })
+}
 
-    match leq_count.cmp(&k) {
-        Ordering::Equal => xs[pivot_idx].clone(),
-        Ordering::Greater => {
-            let mut left = vec![xs[0].clone(); leq_count];
-            let left_slice = UnsafeSlice::new(&mut left);
-            xs.par_iter().enumerate().for_each(|(i, x): (usize, &T)| {
-                if leq_flags[i] == 1 {
-                    unsafe {
-                        left_slice.write(left_locs[i], x.clone());
-                    }
-                }
-            });
-
-            par_quick_select(&left, k, rng)
+fn random_priority(seed: u64, i: usize) -> (u64, usize) {
+    (index_rng(seed, i).next_u64(), i)
+}
+
+#[derive(Clone, Default)]
+pub struct SeqPrioritySampler {
+    config: SamplerConfig,
+}
+
+impl SeqPrioritySampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+impl Configurable for SeqPrioritySampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for SeqPrioritySampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        if n == k {
+            return Ok((0..n).collect());
         }
-        Ordering::Less => {
-            let gt_flags: Vec<usize> = xs
-                .par_iter()
-                .map(|x: &T| match fxhash::hash64(&x).cmp(&pivot_hash) {
-                    Ordering::Greater => 1,
-                    _ => 0,
-                })
+
+        let mut rng = SplitMix64::seed_from_u64(seed);
+        let priorities: Vec<(u64, usize)> = (0..n).map(|i| random_priority(seed, i)).collect();
+        Ok(smallest_k(&priorities, k, &mut rng))
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for SeqPrioritySampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_in_pool(arr.len(), k, seed)
+    }
+
+    fn sample_in_pool(&self, arr: &[T], k: usize, seed: u64) -> Result<Vec<T>, SamplingError> {
+        let indices = self.sample_indices_in_pool(arr.len(), k, seed)?;
+        Ok(indices.iter().map(|&i| arr[i].clone()).collect())
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ParPrioritySampler {
+    config: SamplerConfig,
+    complement: bool,
+    floyd: bool,
+}
+
+impl ParPrioritySampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// With `floyd` on, samples of at most `n / 1024` items are drawn by
+    /// `ParFloydSampler` instead of ranking priorities of the whole
+    /// population. Still in population order, but a different sample than
+    /// `SeqPrioritySampler` draws for the same seed, which is why it is off
+    /// by default.
+    pub fn floyd(mut self, floyd: bool) -> Self {
+        self.floyd = floyd;
+        self
+    }
+
+    /// Sample of at most `k` items that keeps their priorities, so it can be
+    /// merged with sketches of other shards. `offset` is the position of
+    /// `arr` in the whole population: shards sharing a seed then draw the
+    /// priorities the whole population would, and merge to its sketch.
+    /// Unlike `sample`, `k` may exceed `arr.len()`, in which case every item
+    /// is kept.
+    pub fn sketch<T: Clone + Send + Sync>(
+        &self,
+        arr: &[T],
+        k: usize,
+        offset: usize,
+    ) -> PrioritySample<T> {
+        let seed = self.config.resolve_seed();
+        self.config.install(|| {
+            let priorities: Vec<(u64, usize)> = (0..arr.len())
+                .into_par_iter()
+                .map(|i| (random_priority(seed, offset + i).0, i))
                 .collect();
-            let (gt_count, gt_locs) = par_scan(&gt_flags);
-
-            let mut right = vec![xs[0].clone(); gt_count];
-            let right_slice = UnsafeSlice::new(&mut right);
-            xs.par_iter().enumerate().for_each(|(i, x): (usize, &T)| {
-                if gt_flags[i] == 1 {
-                    unsafe {
-                        right_slice.write(gt_locs[i], x.clone());
-                    }
-                }
-            });
-
-            par_quick_select(&right, k - leq_count, rng)
-        }
+            let mut kept = if k == 0 {
+                vec![]
+            } else if k >= arr.len() {
+                priorities
+            } else {
+                par_smallest_k(&priorities, k)
+                    .into_par_iter()
+                    .map(|i| priorities[i])
+                    .collect()
+            };
+            kept.par_sort_unstable();
+
+            let entries = kept
+                .into_par_iter()
+                .map(|(priority, i)| (priority, arr[i].clone()))
+                .collect();
+            PrioritySample::from_sorted(k, entries)
+        })
+    }
+}
+
+impl Complement for ParPrioritySampler {
+    fn complement(mut self, complement: bool) -> Self {
+        self.complement = complement;
+        self
+    }
+
+    fn complements(&self) -> bool {
+        self.complement
     }
 }
 
-pub struct SeqPrioritySampler<T: Clone + Hash + Sized + Send + Sync> {
-    marker: PhantomData<T>,
+impl Configurable for ParPrioritySampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for ParPrioritySampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        if n == k {
+            return Ok((0..n).into_par_iter().collect());
+        }
+        if let Some(indices) = complement_sample_indices(self, n, k, seed) {
+            return indices;
+        }
+        if self.floyd && prefers_floyd(n, k) {
+            let mut indices = ParFloydSampler::new().sample_indices_in_pool(n, k, seed)?;
+            indices.par_sort_unstable();
+            return Ok(indices);
+        }
+
+        let priorities: Vec<(u64, usize)> = (0..n)
+            .into_par_iter()
+            .map(|i| random_priority(seed, i))
+            .collect();
+        Ok(par_smallest_k(&priorities, k))
+    }
 }
 
-impl<T: Clone + Hash + Sized + Send + Sync> Sampler<T> for SeqPrioritySampler<T> {
-    fn sample(arr: &[T], k: usize) -> Option<Vec<T>> {
-        match arr.len().cmp(&k) {
-            Ordering::Less => return None,
-            Ordering::Equal => return Some(arr.to_vec()),
-            Ordering::Greater => {}
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for ParPrioritySampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_in_pool(arr.len(), k, seed)
+    }
+    fn sample_in_pool(&self, arr: &[T], k: usize, seed: u64) -> Result<Vec<T>, SamplingError> {
+        let n = arr.len();
+        if let Some(values) = complement_sample(self, arr, k, seed) {
+            return values;
         }
+        Ok(gather(arr, &self.sample_indices_in_pool(n, k, seed)?))
+    }
+}
+
+/// Consistent sampling: the priority of an element is the hash of its value,
+/// so the same values are picked every run (and across populations sharing
+/// them). Equal values tie and are taken in index order. The seed is unused.
+/// Being value-based, it has no index-only mode.
+#[derive(Clone, Default)]
+pub struct HashPrioritySampler {
+    config: SamplerConfig,
+}
 
-        let mut rng = rand::thread_rng();
-        let kth_element = quick_select(arr, k, &mut rng);
-        let kth_hash = fxhash::hash64(&kth_element);
+impl HashPrioritySampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
 
-        Some(
-            arr.iter()
-                .filter(|x| fxhash::hash64(x) <= kth_hash)
-                .cloned()
-                .collect(),
-        )
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
     }
 }
 
-pub struct ParPrioritySampler<T: Clone + Hash + Sized + Send + Sync> {
-    marker: PhantomData<T>,
+impl Configurable for HashPrioritySampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
 }
 
-impl<T: Clone + Hash + Sized + Send + Sync> Sampler<T> for ParPrioritySampler<T> {
-    fn sample(arr: &[T], k: usize) -> Option<Vec<T>> {
-        match arr.len().cmp(&k) {
-            Ordering::Less => return None,
-            Ordering::Equal => return Some(arr.to_vec()),
-            Ordering::Greater => {}
+impl<T: Clone + Hash + Sized + Send + Sync> Sampler<T> for HashPrioritySampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        _seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        if arr.len() == k {
+            return Ok((0..k).into_par_iter().collect());
         }
 
-        let mut rng = rand::thread_rng();
-        let xs = arr.to_vec();
-        let kth_element = par_quick_select(&xs, k, &mut rng);
-        let kth_hash = fxhash::hash64(&kth_element);
-        let leq_flags: Vec<usize> = xs
+        let priorities: Vec<(u64, usize)> = arr
             .par_iter()
-            .map(|x: &T| match fxhash::hash64(&x).cmp(&kth_hash) {
-                Ordering::Less | Ordering::Equal => 1,
-                _ => 0,
-            })
+            .enumerate()
+            .map(|(i, x)| (fxhash::hash64(x), i))
+            .collect();
+        Ok(par_smallest_k(&priorities, k))
+    }
+}
+
+/// Efraimidis–Spirakis key `u^(1/w)`, kept in log space as `|ln(u)| / w` so the
+/// smallest keys win. Keys are non-negative, so their bit patterns order like
+/// the floats; pairing with the index makes every key distinct. Items with
+/// zero weight get the largest possible key.
+fn weighted_key(weight: f64, seed: u64, i: usize) -> (u64, usize) {
+    if weight > 0.0 {
+        let u = open_unit(&mut index_rng(seed, i));
+        ((u.ln() / weight).abs().to_bits(), i)
+    } else {
+        (u64::MAX, i)
+    }
+}
+
+fn check_weights(weights: &[f64]) -> Result<(), SamplingError> {
+    if weights.par_iter().all(|w| w.is_finite() && *w >= 0.0) {
+        Ok(())
+    } else {
+        Err(SamplingError::InvalidWeights)
+    }
+}
+
+fn check_lengths(n: usize, weights: &[f64]) -> Result<(), SamplingError> {
+    if n == weights.len() {
+        Ok(())
+    } else {
+        Err(SamplingError::LengthMismatch {
+            population: n,
+            weights: weights.len(),
+        })
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct SeqWeightedPrioritySampler {
+    config: SamplerConfig,
+}
+
+impl SeqWeightedPrioritySampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Samples `k` indices without replacement where each draw picks index `i`
+    /// with probability proportional to `weights[i]`. Indices with zero weight
+    /// are never picked, so fewer than `k` positive weights is `KGreaterThanN`.
+    pub fn sample_weighted_indices(
+        &self,
+        weights: &[f64],
+        k: usize,
+    ) -> Result<Vec<usize>, SamplingError> {
+        check_weights(weights)?;
+        check_k(weights.iter().filter(|&&w| w > 0.0).count(), k)?;
+        if k == 0 {
+            return Ok(vec![]);
+        }
+
+        let seed = self.config.resolve_seed();
+        let mut rng = SplitMix64::seed_from_u64(seed);
+        let keys: Vec<(u64, usize)> = weights
+            .iter()
+            .enumerate()
+            .map(|(i, &w)| weighted_key(w, seed, i))
             .collect();
-        let (count, locs) = par_scan(&leq_flags);
-
-        let mut samples = vec![xs[0].clone(); count];
-        let samples_slice = UnsafeSlice::new(&mut samples);
-        xs.par_iter().enumerate().for_each(|(i, x): (usize, &T)| {
-            if leq_flags[i] == 1 {
-                unsafe {
-                    samples_slice.write(locs[i], x.clone());
-                }
+        let mut indices = smallest_k(&keys, k, &mut rng);
+        arrange_indices(&mut indices, self.config.get_order(), seed);
+        Ok(indices)
+    }
+
+    pub fn sample_weighted<T: Clone>(
+        &self,
+        arr: &[T],
+        weights: &[f64],
+        k: usize,
+    ) -> Result<Vec<T>, SamplingError> {
+        check_lengths(arr.len(), weights)?;
+        let indices = self.sample_weighted_indices(weights, k)?;
+        Ok(indices.iter().map(|&i| arr[i].clone()).collect())
+    }
+
+    pub fn sample_weighted_by<T: Clone, F: Fn(&T) -> f64>(
+        &self,
+        arr: &[T],
+        k: usize,
+        weight: F,
+    ) -> Result<Vec<T>, SamplingError> {
+        let weights: Vec<f64> = arr.iter().map(weight).collect();
+        self.sample_weighted(arr, &weights, k)
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct WeightedPrioritySampler {
+    config: SamplerConfig,
+}
+
+impl WeightedPrioritySampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Parallel version of `SeqWeightedPrioritySampler::sample_weighted_indices`;
+    /// gives the same sample for the same seed.
+    pub fn sample_weighted_indices(
+        &self,
+        weights: &[f64],
+        k: usize,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.config.install(|| {
+            check_weights(weights)?;
+            check_k(weights.par_iter().filter(|&&w| w > 0.0).count(), k)?;
+            if k == 0 {
+                return Ok(vec![]);
             }
-        });
 
-        Some(samples)
+            let seed = self.config.resolve_seed();
+            let keys: Vec<(u64, usize)> = weights
+                .par_iter()
+                .enumerate()
+                .map(|(i, &w)| weighted_key(w, seed, i))
+                .collect();
+            let mut indices = par_smallest_k(&keys, k);
+            arrange_indices(&mut indices, self.config.get_order(), seed);
+            Ok(indices)
+        })
+    }
+
+    pub fn sample_weighted<T: Clone + Send + Sync>(
+        &self,
+        arr: &[T],
+        weights: &[f64],
+        k: usize,
+    ) -> Result<Vec<T>, SamplingError> {
+        check_lengths(arr.len(), weights)?;
+        let indices = self.sample_weighted_indices(weights, k)?;
+        Ok(self.config.install(|| gather(arr, &indices)))
+    }
+
+    pub fn sample_weighted_by<T: Clone + Send + Sync, F: Fn(&T) -> f64 + Send + Sync>(
+        &self,
+        arr: &[T],
+        k: usize,
+        weight: F,
+    ) -> Result<Vec<T>, SamplingError> {
+        let weights: Vec<f64> = self.config.install(|| arr.par_iter().map(weight).collect());
+        self.sample_weighted(arr, &weights, k)
     }
 }
 
@@ -162,7 +464,7 @@ mod test {
         let k = 50_000;
         let sample_size = 10_000_000;
         let population = (0..sample_size).collect::<Vec<i32>>();
-        let samples = ParPrioritySampler::sample(&population, k);
+        let samples = ParPrioritySampler::new().sample(&population, k);
 
         assert_eq!(k, samples.unwrap().len());
     }
@@ -177,8 +479,197 @@ mod test {
         let population = (0..sample_size)
             .map(|_| rand::random::<i32>())
             .collect::<Vec<i32>>();
-        let samples = ParPrioritySampler::sample(&population, k);
+        let samples = ParPrioritySampler::new().sample(&population, k);
 
         assert_eq!(k, samples.unwrap().len());
     }
+
+    #[test]
+    fn ps_seeded_across_threads() {
+        use super::{ParPrioritySampler, SeqPrioritySampler};
+        use crate::samplers::sampl_interface::Sampler;
+
+        let population = (0..100_000).collect::<Vec<i32>>();
+        let k = 1_000;
+        let seq_samples = SeqPrioritySampler::new().sample_seeded(&population, k, 42);
+        [1, 4].iter().for_each(|&threads| {
+            let pool = rayon::ThreadPoolBuilder::new()
+                .num_threads(threads)
+                .build()
+                .unwrap();
+            let par_samples =
+                pool.install(|| ParPrioritySampler::new().sample_seeded(&population, k, 42));
+            assert_eq!(seq_samples, par_samples);
+        });
+    }
+
+    #[test]
+    fn ps_tiny_sample_is_seq() {
+        use super::{ParPrioritySampler, SeqPrioritySampler};
+        use crate::samplers::sampl_interface::{Sampler, SamplerConfig};
+
+        // small enough for Floyd's algorithm, which only runs when asked to
+        let population = (0..1 << 20).collect::<Vec<i32>>();
+        let k = 100;
+        let config = SamplerConfig::new().seed(42);
+        let seq_samples = SeqPrioritySampler::new()
+            .with_config(config.clone())
+            .sample(&population, k)
+            .unwrap();
+        let par_samples = ParPrioritySampler::new()
+            .with_config(config.clone())
+            .sample(&population, k)
+            .unwrap();
+        assert_eq!(seq_samples, par_samples);
+        assert!(par_samples.windows(2).all(|w| w[0] < w[1]));
+
+        let floyd_samples = ParPrioritySampler::new()
+            .with_config(config)
+            .floyd(true)
+            .sample(&population, k)
+            .unwrap();
+        assert_eq!(k, floyd_samples.len());
+        assert!(floyd_samples.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn ps_duplicates_are_independent() {
+        use super::ParPrioritySampler;
+        use crate::samplers::sampl_interface::{Sampler, SamplerConfig};
+
+        let population = (0..100_000).map(|i| i % 10).collect::<Vec<i32>>();
+        let first = ParPrioritySampler::new()
+            .with_config(SamplerConfig::new().seed(1))
+            .sample(&population, 1_000)
+            .unwrap();
+        let second = ParPrioritySampler::new()
+            .with_config(SamplerConfig::new().seed(2))
+            .sample(&population, 1_000)
+            .unwrap();
+
+        assert_eq!(1_000, first.len());
+        assert_eq!(1_000, second.len());
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn hash_ps_is_consistent() {
+        use super::HashPrioritySampler;
+        use crate::samplers::sampl_interface::{Sampler, SamplerConfig};
+
+        let population = (0..100_000).collect::<Vec<i32>>();
+        let first = HashPrioritySampler::new()
+            .with_config(SamplerConfig::new().seed(1))
+            .sample(&population, 1_000);
+        let second = HashPrioritySampler::new()
+            .with_config(SamplerConfig::new().seed(2))
+            .sample(&population, 1_000);
+
+        assert_eq!(1_000, first.as_ref().unwrap().len());
+        assert_eq!(first, second);
+    }
+
+    #[test]
+    fn ps_exact_k_on_equal_values() {
+        use super::{HashPrioritySampler, ParPrioritySampler, SeqPrioritySampler};
+        use crate::samplers::sampl_interface::Sampler;
+
+        let population = vec![7i32; 100_000];
+        let k = 1_000;
+        let samplers: Vec<Box<dyn Sampler<i32>>> = vec![
+            Box::new(SeqPrioritySampler::new()),
+            Box::new(ParPrioritySampler::new()),
+            Box::new(HashPrioritySampler::new()),
+        ];
+        samplers.iter().for_each(|sampler| {
+            assert_eq!(k, sampler.sample(&population, k).unwrap().len());
+        });
+    }
+
+    #[test]
+    fn hash_ps_ties_give_exact_k() {
+        use super::HashPrioritySampler;
+        use crate::samplers::sampl_interface::Sampler;
+
+        let population = (0..1_000).map(|i| i % 2).collect::<Vec<i32>>();
+        let samples = HashPrioritySampler::new().sample(&population, 10).unwrap();
+        let smaller_hash = if fxhash::hash64(&0) < fxhash::hash64(&1) {
+            0
+        } else {
+            1
+        };
+
+        assert_eq!(vec![smaller_hash; 10], samples);
+    }
+
+    #[test]
+    fn weighted_seq_is_par() {
+        use super::{SeqWeightedPrioritySampler, WeightedPrioritySampler};
+        use crate::samplers::sampl_interface::SamplerConfig;
+
+        let population = (0..100_000).collect::<Vec<i32>>();
+        let config = SamplerConfig::new().seed(11);
+        let seq_samples = SeqWeightedPrioritySampler::new()
+            .with_config(config.clone())
+            .sample_weighted_by(&population, 5_000, |&x| (x % 7) as f64)
+            .unwrap();
+        let par_samples = WeightedPrioritySampler::new()
+            .with_config(config)
+            .sample_weighted_by(&population, 5_000, |&x| (x % 7) as f64)
+            .unwrap();
+
+        assert_eq!(5_000, seq_samples.len());
+        assert_eq!(seq_samples, par_samples);
+        assert!(seq_samples.iter().all(|x| x % 7 != 0));
+    }
+
+    #[test]
+    fn weighted_follows_weights() {
+        use super::WeightedPrioritySampler;
+        use crate::samplers::sampl_interface::SamplerConfig;
+
+        let population = vec![0, 1, 2, 3, 4];
+        let weights = vec![1.0, 1.0, 1.0, 1.0, 4.0];
+        let trials = 4_000;
+        let heavy_count = (0..trials)
+            .filter(|&seed| {
+                let sample = WeightedPrioritySampler::new()
+                    .with_config(SamplerConfig::new().seed(seed))
+                    .sample_weighted(&population, &weights, 1)
+                    .unwrap();
+                sample[0] == 4
+            })
+            .count();
+
+        let heavy_ratio = heavy_count as f64 / trials as f64;
+        assert!((heavy_ratio - 0.5).abs() < 0.05);
+    }
+
+    #[test]
+    fn weighted_rejects_bad_weights() {
+        use super::WeightedPrioritySampler;
+        use crate::samplers::sampl_error::SamplingError;
+
+        let population = vec![0, 1, 2];
+        let sampler = WeightedPrioritySampler::new();
+        assert_eq!(
+            sampler.sample_weighted(&population, &[1.0, -1.0, 1.0], 1),
+            Err(SamplingError::InvalidWeights)
+        );
+        assert_eq!(
+            sampler.sample_weighted(&population, &[1.0, f64::NAN, 1.0], 1),
+            Err(SamplingError::InvalidWeights)
+        );
+        assert_eq!(
+            sampler.sample_weighted(&population, &[1.0, 0.0, 1.0], 3),
+            Err(SamplingError::KGreaterThanN { k: 3, n: 2 })
+        );
+        assert_eq!(
+            sampler.sample_weighted(&population, &[1.0, 1.0], 1),
+            Err(SamplingError::LengthMismatch {
+                population: 3,
+                weights: 2
+            })
+        );
+    }
 }
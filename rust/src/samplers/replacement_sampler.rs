@@ -0,0 +1,158 @@
+use rand::{Rng, RngCore};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::samplers::{
+    sampl_error::SamplingError,
+    sampl_interface::{arrange_indices, sample_in_order, Configurable, Sampler, SamplerConfig},
+};
+use crate::utils::rng::index_rng;
+
+/// Draw `i` only depends on `seed` and `i`, like `generate_swaps`.
+fn generate_draws(n: usize, k: usize, seed: u64) -> Vec<usize> {
+    (0..k)
+        .into_par_iter()
+        .map(|i| index_rng(seed, i).gen_range(0..n))
+        .collect()
+}
+
+fn check_population(n: usize, k: usize) -> Result<(), SamplingError> {
+    if n == 0 && k > 0 {
+        Err(SamplingError::EmptyPopulation)
+    } else {
+        Ok(())
+    }
+}
+
+/// Sampling with replacement: `k` independent uniform draws, so `k` may
+/// exceed the population size and items can repeat.
+#[derive(Clone, Default)]
+pub struct ReplacementSampler {
+    config: SamplerConfig,
+}
+
+impl ReplacementSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// `k` indices drawn uniformly from `0..n` with replacement.
+    pub fn sample_indices(&self, n: usize, k: usize) -> Result<Vec<usize>, SamplingError> {
+        check_population(n, k)?;
+        let seed = self.config.resolve_seed();
+        Ok(self.config.install(|| {
+            let mut draws = generate_draws(n, k, seed);
+            arrange_indices(&mut draws, self.config.get_order(), seed);
+            draws
+        }))
+    }
+
+    /// `replicates` bootstrap resamples of `arr`, each given as `k` indices
+    /// into it (use `k = arr.len()` for the classic bootstrap). Every
+    /// replicate gets its own stream, so they are independent of each other
+    /// and of the thread count.
+    pub fn bootstrap<T>(
+        &self,
+        arr: &[T],
+        replicates: usize,
+        k: usize,
+    ) -> Result<Vec<Vec<usize>>, SamplingError> {
+        let n = arr.len();
+        check_population(n, k)?;
+        let seed = self.config.resolve_seed();
+        Ok(self.config.install(|| {
+            (0..replicates)
+                .into_par_iter()
+                .map(|r| {
+                    let replicate_seed = index_rng(seed, r).next_u64();
+                    let mut draws = generate_draws(n, k, replicate_seed);
+                    arrange_indices(&mut draws, self.config.get_order(), replicate_seed);
+                    draws
+                })
+                .collect()
+        }))
+    }
+}
+
+impl Configurable for ReplacementSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for ReplacementSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        Ok(generate_draws(arr.len(), k, seed))
+    }
+
+    /// Unlike the default, allows `k > arr.len()`.
+    fn sample_seeded(&self, arr: &[T], k: usize, seed: u64) -> Result<Vec<T>, SamplingError> {
+        check_population(arr.len(), k)?;
+        self.config.install(|| sample_in_order(self, arr, k, seed))
+    }
+}
+
+mod test {
+    #[test]
+    fn replacement_allows_k_over_n() {
+        use super::ReplacementSampler;
+        use crate::samplers::{sampl_error::SamplingError, sampl_interface::Sampler};
+
+        let xs: Vec<i32> = (0..10).collect();
+        let sampler = ReplacementSampler::new();
+        let samples = sampler.sample(&xs, 1_000).unwrap();
+        assert_eq!(1_000, samples.len());
+        assert!(samples.iter().all(|x| xs.contains(x)));
+
+        let empty: Vec<i32> = vec![];
+        assert_eq!(
+            sampler.sample(&empty, 1),
+            Err(SamplingError::EmptyPopulation)
+        );
+        assert_eq!(sampler.sample(&empty, 0), Ok(vec![]));
+    }
+
+    #[test]
+    fn replacement_is_uniform() {
+        use super::ReplacementSampler;
+        use crate::samplers::sampl_interface::test::{assert_uniform, inclusion_counts};
+
+        // one long sample with replacement draws every index independently
+        let (n, k) = (10, 100_000);
+        let counts = inclusion_counts(n, k, 1, |_| {
+            ReplacementSampler::new().sample_indices(n, k).unwrap()
+        });
+        assert_uniform(&counts, (k / n) as f64, 0.05);
+    }
+
+    #[test]
+    fn bootstrap_seeded_across_threads() {
+        use super::ReplacementSampler;
+        use crate::samplers::sampl_interface::SamplerConfig;
+
+        let xs: Vec<f64> = (0..1_000).map(|i| i as f64).collect();
+        let replicates = [1, 4]
+            .iter()
+            .map(|&threads| {
+                ReplacementSampler::new()
+                    .with_config(SamplerConfig::new().seed(17).num_threads(threads).unwrap())
+                    .bootstrap(&xs, 50, xs.len())
+                    .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(replicates[0], replicates[1]);
+        assert_eq!(50, replicates[0].len());
+        assert!(replicates[0].iter().all(|r| r.len() == xs.len()));
+        assert_ne!(replicates[0][0], replicates[0][1]);
+    }
+}
@@ -0,0 +1,357 @@
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
+
+use rand::{seq::SliceRandom, Rng, SeedableRng};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::samplers::{
+    sampl_error::{check_k, SamplingError},
+    sampl_interface::{
+        order_seed, Configurable, IndexSampler, OutputOrder, Sampler, SamplerConfig,
+    },
+};
+use crate::utils::rng::{index_rng, open_unit, SplitMix64};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ReservoirAlgorithm {
+    /// Vitter's Algorithm R: one random draw per item.
+    R,
+    /// Li's Algorithm L: draws how many items to skip before the next
+    /// replacement, so the work is O(k (1 + log(n / k))) random draws.
+    #[default]
+    L,
+}
+
+struct Reservoir<T> {
+    items: Vec<T>,
+    k: usize,
+    seen: usize,
+    algorithm: ReservoirAlgorithm,
+    rng: SplitMix64,
+    w: f64,
+    skip: usize,
+}
+
+impl<T> Reservoir<T> {
+    fn new(k: usize, algorithm: ReservoirAlgorithm, rng: SplitMix64) -> Self {
+        Self {
+            items: Vec::with_capacity(k),
+            k,
+            seen: 0,
+            algorithm,
+            rng,
+            w: 1.0,
+            skip: 0,
+        }
+    }
+
+    fn schedule_skip(&mut self) {
+        if self.algorithm == ReservoirAlgorithm::L {
+            self.w *= (open_unit(&mut self.rng).ln() / self.k as f64).exp();
+            self.skip = (open_unit(&mut self.rng).ln() / (1.0 - self.w).ln()).floor() as usize;
+        }
+    }
+
+    fn offer(&mut self, x: T) {
+        self.seen += 1;
+        if self.items.len() < self.k {
+            self.items.push(x);
+            if self.items.len() == self.k {
+                self.schedule_skip();
+            }
+            return;
+        }
+
+        match self.algorithm {
+            ReservoirAlgorithm::R => {
+                let j = self.rng.gen_range(0..self.seen);
+                if j < self.k {
+                    self.items[j] = x;
+                }
+            }
+            ReservoirAlgorithm::L => {
+                if self.skip > 0 {
+                    self.skip -= 1;
+                } else {
+                    let j = self.rng.gen_range(0..self.k);
+                    self.items[j] = x;
+                    self.schedule_skip();
+                }
+            }
+        }
+    }
+
+    /// Feeds a whole iterator, letting Algorithm L jump over rejected items
+    /// with `nth`. Items skipped at the very end are not counted in `seen`,
+    /// which only matters once the reservoir is already full.
+    fn extend<I: Iterator<Item = T>>(&mut self, mut iter: I) {
+        while let Some(x) = iter.nth(self.skip) {
+            self.seen += self.skip;
+            self.skip = 0;
+            self.offer(x);
+        }
+    }
+
+    /// Combines two reservoirs over disjoint streams into one over their
+    /// union. The number of items kept from each side follows the
+    /// hypergeometric distribution of drawing `k` out of both streams. The
+    /// result is only meant to be read, not fed more items.
+    fn merge(mut self, mut other: Self) -> Self {
+        let take = self.k.min(self.seen + other.seen);
+        let (mut left_self, mut left_other) = (self.seen, other.seen);
+        let mut from_self = 0;
+        (0..take).for_each(|_| {
+            if self.rng.gen_range(0..left_self + left_other) < left_self {
+                from_self += 1;
+                left_self -= 1;
+            } else {
+                left_other -= 1;
+            }
+        });
+
+        let from_other = take - from_self;
+        keep_random(&mut self.items, from_self, &mut self.rng);
+        keep_random(&mut other.items, from_other, &mut self.rng);
+        self.items.append(&mut other.items);
+        self.seen += other.seen;
+        self
+    }
+}
+
+/// Keeps a uniformly random subset of `m` items (partial Fisher–Yates).
+fn keep_random<T>(items: &mut Vec<T>, m: usize, rng: &mut SplitMix64) {
+    (0..m).for_each(|i| {
+        let j = rng.gen_range(i..items.len());
+        items.swap(i, j);
+    });
+    items.truncate(m);
+}
+
+/// Uniform sampling without replacement from streams of unknown length in
+/// O(k) memory. The sample comes back in no particular order.
+#[derive(Clone, Default)]
+pub struct ReservoirSampler {
+    config: SamplerConfig,
+    algorithm: ReservoirAlgorithm,
+}
+
+impl ReservoirSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: ReservoirAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    /// The reservoir as filled, in no particular order.
+    fn fill<T, I: IntoIterator<Item = T>>(
+        &self,
+        iter: I,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<T>, SamplingError> {
+        if k == 0 {
+            return Ok(vec![]);
+        }
+
+        let mut reservoir = Reservoir::new(k, self.algorithm, SplitMix64::seed_from_u64(seed));
+        reservoir.extend(iter.into_iter());
+        check_k(reservoir.seen, k)?;
+        Ok(reservoir.items)
+    }
+
+    /// Stable order tags every item with its stream position, which leaves
+    /// the draws, and so the sample, unchanged.
+    pub fn sample_iter_seeded<T, I: IntoIterator<Item = T>>(
+        &self,
+        iter: I,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<T>, SamplingError> {
+        match self.config.get_order() {
+            OutputOrder::Any => self.fill(iter, k, seed),
+            OutputOrder::Random => {
+                let mut samples = self.fill(iter, k, seed)?;
+                samples.shuffle(&mut SplitMix64::seed_from_u64(order_seed(seed)));
+                Ok(samples)
+            }
+            OutputOrder::Stable | OutputOrder::SortedByIndex => {
+                let mut tagged = self.fill(iter.into_iter().enumerate(), k, seed)?;
+                tagged.sort_unstable_by_key(|&(i, _)| i);
+                Ok(tagged.into_iter().map(|(_, x)| x).collect())
+            }
+        }
+    }
+
+    /// Consumes `iter` once; errors if it yields fewer than `k` items.
+    pub fn sample_iter<T, I: IntoIterator<Item = T>>(
+        &self,
+        iter: I,
+        k: usize,
+    ) -> Result<Vec<T>, SamplingError> {
+        self.sample_iter_seeded(iter, k, self.config.resolve_seed())
+    }
+
+    /// Fills one reservoir per rayon split and merges them pairwise. How the
+    /// input gets split depends on scheduling, so unlike `sample_iter` the
+    /// result is not reproducible from the seed alone. The splits don't know
+    /// their positions, so only `Any` and `Random` order are supported.
+    pub fn par_sample_iter<T: Send, I: IntoParallelIterator<Item = T>>(
+        &self,
+        iter: I,
+        k: usize,
+    ) -> Result<Vec<T>, SamplingError> {
+        let order = self.config.get_order();
+        if matches!(order, OutputOrder::Stable | OutputOrder::SortedByIndex) {
+            return Err(SamplingError::UnsupportedOrder(order));
+        }
+        if k == 0 {
+            return Ok(vec![]);
+        }
+
+        let seed = self.config.resolve_seed();
+        let split_count = AtomicUsize::new(0);
+        let iter = iter.into_par_iter();
+        let reservoir = self.config.install(|| {
+            iter.fold(
+                || {
+                    let split = split_count.fetch_add(1, AtomicOrdering::Relaxed);
+                    Reservoir::new(k, self.algorithm, index_rng(seed, split))
+                },
+                |mut reservoir, x| {
+                    reservoir.offer(x);
+                    reservoir
+                },
+            )
+            .reduce_with(|a, b| a.merge(b))
+        });
+
+        match reservoir {
+            Some(mut reservoir) => {
+                check_k(reservoir.seen, k)?;
+                if order == OutputOrder::Random {
+                    let mut rng = SplitMix64::seed_from_u64(order_seed(seed));
+                    reservoir.items.shuffle(&mut rng);
+                }
+                Ok(reservoir.items)
+            }
+            None => Err(SamplingError::EmptyPopulation),
+        }
+    }
+}
+
+impl Configurable for ReservoirSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for ReservoirSampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.fill(0..n, k, seed)
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for ReservoirSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.fill(0..arr.len(), k, seed)
+    }
+
+    fn sample_in_pool(&self, arr: &[T], k: usize, seed: u64) -> Result<Vec<T>, SamplingError> {
+        self.fill(arr.iter().cloned(), k, seed)
+    }
+}
+
+mod test {
+    #[test]
+    fn reservoir_is_uniform() {
+        use super::{ReservoirAlgorithm, ReservoirSampler};
+        use crate::samplers::sampl_interface::test::{assert_uniform, inclusion_counts};
+
+        let (n, k, trials) = (20, 5, 20_000);
+        [ReservoirAlgorithm::R, ReservoirAlgorithm::L]
+            .iter()
+            .for_each(|&algorithm| {
+                let sampler = ReservoirSampler::new().algorithm(algorithm);
+                let counts = inclusion_counts(n, k, trials, |seed| {
+                    sampler.sample_iter_seeded(0..n, k, seed).unwrap()
+                });
+                assert_uniform(&counts, (trials as usize * k / n) as f64, 0.05);
+            });
+    }
+
+    #[test]
+    fn par_reservoir_is_uniform() {
+        use super::ReservoirSampler;
+        use crate::samplers::sampl_interface::{
+            test::{assert_uniform, inclusion_counts},
+            SamplerConfig,
+        };
+
+        let (n, k, trials) = (20, 5, 10_000);
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let counts = pool.install(|| {
+            inclusion_counts(n, k, trials, |seed| {
+                ReservoirSampler::new()
+                    .with_config(SamplerConfig::new().seed(seed))
+                    .par_sample_iter(0..n, k)
+                    .unwrap()
+            })
+        });
+        assert_uniform(&counts, (trials as usize * k / n) as f64, 0.07);
+    }
+
+    #[test]
+    fn reservoir_big_stream() {
+        use super::ReservoirSampler;
+        use std::collections::HashSet;
+
+        let n = 1_000_000;
+        let k = 1_000;
+        let seq_samples = ReservoirSampler::new().sample_iter(0..n, k).unwrap();
+        let par_samples = ReservoirSampler::new().par_sample_iter(0..n, k).unwrap();
+        [seq_samples, par_samples].iter().for_each(|samples| {
+            assert_eq!(k, samples.iter().collect::<HashSet<_>>().len());
+        });
+    }
+
+    #[test]
+    fn reservoir_short_stream() {
+        use super::ReservoirSampler;
+        use crate::samplers::sampl_error::SamplingError;
+
+        let sampler = ReservoirSampler::new();
+        assert_eq!(
+            sampler.sample_iter(0..3, 5),
+            Err(SamplingError::KGreaterThanN { k: 5, n: 3 })
+        );
+        assert_eq!(
+            sampler.par_sample_iter(0..3, 5),
+            Err(SamplingError::KGreaterThanN { k: 5, n: 3 })
+        );
+        assert_eq!(
+            sampler.sample_iter(0..0, 1),
+            Err(SamplingError::EmptyPopulation)
+        );
+        assert_eq!(sampler.sample_iter(0..3, 3).unwrap().len(), 3);
+    }
+}
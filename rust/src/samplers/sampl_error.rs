@@ -0,0 +1,73 @@
+use std::fmt;
+
+use rayon::ThreadPoolBuildError;
+
+use crate::samplers::sampl_interface::OutputOrder;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SamplingError {
+    /// Asked for more items than there are to pick from.
+    KGreaterThanN {
+        k: usize,
+        n: usize,
+    },
+    /// Asked for at least one item from an empty population.
+    EmptyPopulation,
+    /// A weight is negative, NaN or infinite.
+    InvalidWeights,
+    /// The weights and the population have different lengths.
+    LengthMismatch {
+        population: usize,
+        weights: usize,
+    },
+    /// The requested output order cannot be produced by this call.
+    UnsupportedOrder(OutputOrder),
+    ThreadPoolError(String),
+}
+
+impl fmt::Display for SamplingError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SamplingError::KGreaterThanN { k, n } => {
+                write!(f, "cannot sample {} items out of {}", k, n)
+            }
+            SamplingError::EmptyPopulation => write!(f, "population is empty"),
+            SamplingError::InvalidWeights => {
+                write!(f, "weights must be finite and non-negative")
+            }
+            SamplingError::LengthMismatch {
+                population,
+                weights,
+            } => write!(
+                f,
+                "{} weights given for a population of {}",
+                weights, population
+            ),
+            SamplingError::UnsupportedOrder(order) => {
+                write!(f, "output order {:?} is not supported here", order)
+            }
+            SamplingError::ThreadPoolError(msg) => write!(f, "thread pool error: {}", msg),
+        }
+    }
+}
+
+impl std::error::Error for SamplingError {}
+
+impl From<ThreadPoolBuildError> for SamplingError {
+    fn from(err: ThreadPoolBuildError) -> Self {
+        SamplingError::ThreadPoolError(err.to_string())
+    }
+}
+
+/// Checks that `k` items can be drawn without replacement from `n`.
+pub fn check_k(n: usize, k: usize) -> Result<(), SamplingError> {
+    if k == 0 {
+        Ok(())
+    } else if n == 0 {
+        Err(SamplingError::EmptyPopulation)
+    } else if k > n {
+        Err(SamplingError::KGreaterThanN { k, n })
+    } else {
+        Ok(())
+    }
+}
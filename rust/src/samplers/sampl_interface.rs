@@ -1,3 +1,687 @@
-pub trait Sampler<T: Clone> {
-    fn sample(arr: &[T], k: usize) -> Option<Vec<T>>;
+use std::{str::FromStr, sync::Arc};
+
+use rand::RngCore;
+use rayon::{
+    iter::{IntoParallelRefIterator, ParallelIterator},
+    slice::ParallelSliceMut,
+    ThreadPool,
+};
+
+use super::{
+    auto_sampler::AutoSampler,
+    floyd_sampler::{FloydSampler, ParFloydSampler},
+    hypergeometric_sampler::HypergeometricSampler,
+    naive_sampler::NaiveSampler,
+    perm_sampler::{
+        par_shuffle_seeded, FullPermutationSampler, PermutationSampler, SeqPermutationSampler,
+    },
+    priority_sampler::{ParPrioritySampler, SeqPrioritySampler},
+    replacement_sampler::ReplacementSampler,
+    reservoir_sampler::ReservoirSampler,
+    sampl_error::{check_k, SamplingError},
+    selection_sampler::{ParSelectionSampler, SelectionSampler},
+};
+use crate::utils::{cwslice::UnsafeSlice, pack::par_pack_range, rng::index_rng};
+
+/// The order a sample comes back in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputOrder {
+    /// Whatever the algorithm produces, at no extra cost. Priority samplers
+    /// give population order and permutation samplers a random one, but
+    /// others make no promise.
+    #[default]
+    Any,
+    /// Uniformly random order.
+    Random,
+    /// The sampled items keep their relative order from the population.
+    Stable,
+    /// Indices in increasing order. Same result as `Stable`, named for the
+    /// index-returning calls.
+    SortedByIndex,
+}
+
+/// Puts sampled positions into `order`, in parallel. `seed` must be the one
+/// the positions were drawn with; the shuffle derives its own from it.
+pub(crate) fn arrange_indices(indices: &mut [usize], order: OutputOrder, seed: u64) {
+    match order {
+        OutputOrder::Any => {}
+        OutputOrder::Random => par_shuffle_seeded(indices, order_seed(seed)),
+        OutputOrder::Stable | OutputOrder::SortedByIndex => indices.par_sort_unstable(),
+    }
+}
+
+/// Seed for shuffling a sample, so it is independent of the sampling itself.
+pub(crate) fn order_seed(seed: u64) -> u64 {
+    index_rng(seed, usize::MAX).next_u64()
+}
+
+/// Settings shared by every sampler.
+#[derive(Clone, Default)]
+pub struct SamplerConfig {
+    seed: Option<u64>,
+    thread_pool: Option<Arc<ThreadPool>>,
+    order: OutputOrder,
+}
+
+impl SamplerConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fixes the seed used by `Sampler::sample`.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Runs the sampler inside `pool` instead of the global rayon pool.
+    pub fn thread_pool(mut self, pool: Arc<ThreadPool>) -> Self {
+        self.thread_pool = Some(pool);
+        self
+    }
+
+    /// Runs the sampler inside a fresh pool of `num_threads` threads.
+    pub fn num_threads(self, num_threads: usize) -> Result<Self, SamplingError> {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()?;
+        Ok(self.thread_pool(Arc::new(pool)))
+    }
+
+    pub fn order(mut self, order: OutputOrder) -> Self {
+        self.order = order;
+        self
+    }
+
+    pub fn get_seed(&self) -> Option<u64> {
+        self.seed
+    }
+
+    pub fn get_order(&self) -> OutputOrder {
+        self.order
+    }
+
+    /// The configured seed, or a fresh random one if none was set.
+    pub fn resolve_seed(&self) -> u64 {
+        self.seed.unwrap_or_else(rand::random)
+    }
+
+    pub fn install<R: Send>(&self, f: impl FnOnce() -> R + Send) -> R {
+        match &self.thread_pool {
+            Some(pool) => pool.install(f),
+            None => f(),
+        }
+    }
+}
+
+pub trait Configurable: Send + Sync {
+    fn config(&self) -> &SamplerConfig;
+}
+
+pub trait Sampler<T: Clone + Send + Sync>: Configurable {
+    /// The algorithm itself, run on whatever rayon pool is current, giving
+    /// the positions in `arr` it picked. Only called with `0 < k <= arr.len()`.
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError>;
+
+    /// The values at `sample_positions_in_pool`, for samplers that have a
+    /// faster way to produce the values directly.
+    fn sample_in_pool(&self, arr: &[T], k: usize, seed: u64) -> Result<Vec<T>, SamplingError> {
+        let positions = self.sample_positions_in_pool(arr, k, seed)?;
+        Ok(gather(arr, &positions))
+    }
+
+    /// Same `seed` gives the same sample, regardless of the number of threads.
+    /// `AutoSampler` also needs the same cost model and planned thread count.
+    fn sample_seeded(&self, arr: &[T], k: usize, seed: u64) -> Result<Vec<T>, SamplingError> {
+        check_k(arr.len(), k)?;
+        if k == 0 {
+            return Ok(vec![]);
+        }
+        self.config()
+            .install(|| sample_in_order(self, arr, k, seed))
+    }
+
+    fn sample(&self, arr: &[T], k: usize) -> Result<Vec<T>, SamplingError> {
+        self.sample_seeded(arr, k, self.config().resolve_seed())
+    }
+
+    fn sample_with_rng(
+        &self,
+        arr: &[T],
+        k: usize,
+        rng: &mut dyn RngCore,
+    ) -> Result<Vec<T>, SamplingError> {
+        self.sample_seeded(arr, k, rng.next_u64())
+    }
+}
+
+/// Samples positions instead of values, so the population is never touched
+/// and its elements need not be `Clone`.
+pub trait IndexSampler: Configurable {
+    /// Only called with `0 < k <= n`.
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError>;
+
+    fn sample_indices_seeded(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        check_k(n, k)?;
+        if k == 0 {
+            return Ok(vec![]);
+        }
+        self.config().install(|| {
+            let mut indices = self.sample_indices_in_pool(n, k, seed)?;
+            arrange_indices(&mut indices, self.config().get_order(), seed);
+            Ok(indices)
+        })
+    }
+
+    /// `k` distinct indices from `0..n`.
+    fn sample_indices(&self, n: usize, k: usize) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_seeded(n, k, self.config().resolve_seed())
+    }
+
+    fn sample_refs<'a, T: Sync>(&self, arr: &'a [T], k: usize) -> Result<Vec<&'a T>, SamplingError>
+    where
+        Self: Sized,
+    {
+        let indices = self.sample_indices(arr.len(), k)?;
+        Ok(indices.par_iter().map(|&i| &arr[i]).collect())
+    }
+}
+
+/// Runs `sampler` and puts the result in its configured order. Random order
+/// shuffles the values; stable order sorts the positions before gathering.
+pub(crate) fn sample_in_order<T, S>(
+    sampler: &S,
+    arr: &[T],
+    k: usize,
+    seed: u64,
+) -> Result<Vec<T>, SamplingError>
+where
+    T: Clone + Send + Sync,
+    S: Sampler<T> + ?Sized,
+{
+    match sampler.config().get_order() {
+        OutputOrder::Any => sampler.sample_in_pool(arr, k, seed),
+        OutputOrder::Random => {
+            let mut samples = sampler.sample_in_pool(arr, k, seed)?;
+            par_shuffle_seeded(&mut samples, order_seed(seed));
+            Ok(samples)
+        }
+        order @ (OutputOrder::Stable | OutputOrder::SortedByIndex) => {
+            let mut positions = sampler.sample_positions_in_pool(arr, k, seed)?;
+            arrange_indices(&mut positions, order, seed);
+            Ok(gather(arr, &positions))
+        }
+    }
+}
+
+/// Clones `arr[i]` for every `i` in `indices`, in parallel.
+pub fn gather<T: Clone + Send + Sync>(arr: &[T], indices: &[usize]) -> Vec<T> {
+    indices.par_iter().map(|&i| arr[i].clone()).collect()
+}
+
+/// Complement mode: with it on, a sample of more than half the population
+/// draws the `n - k` items to leave out instead and returns the rest, in
+/// population order.
+pub trait Complement: IndexSampler + Sized {
+    /// Turns complement mode on or off.
+    fn complement(self, complement: bool) -> Self;
+
+    fn complements(&self) -> bool;
+}
+
+/// Complement mode pays off once more than half the population is sampled.
+fn prefers_complement(n: usize, k: usize) -> bool {
+    k > n / 2
+}
+
+/// The indices `sampler` gives in complement mode, or `None` if the mode is
+/// off or does not pay off for this `k`.
+pub(crate) fn complement_sample_indices<S: Complement>(
+    sampler: &S,
+    n: usize,
+    k: usize,
+    seed: u64,
+) -> Option<Result<Vec<usize>, SamplingError>> {
+    (sampler.complements() && prefers_complement(n, k)).then(|| {
+        let excluded = sampler.sample_indices_in_pool(n, n - k, seed)?;
+        Ok(complement_indices(n, &excluded))
+    })
+}
+
+/// `complement_sample_indices`, but filtering the values of `arr` directly.
+pub(crate) fn complement_sample<S: Complement, T: Clone + Send + Sync>(
+    sampler: &S,
+    arr: &[T],
+    k: usize,
+    seed: u64,
+) -> Option<Result<Vec<T>, SamplingError>> {
+    let n = arr.len();
+    (sampler.complements() && prefers_complement(n, k)).then(|| {
+        let excluded = sampler.sample_indices_in_pool(n, n - k, seed)?;
+        Ok(complement_values(arr, &excluded))
+    })
+}
+
+/// Flags the positions in `0..n` that are not in `excluded`, which must be
+/// distinct.
+fn kept_mask(n: usize, excluded: &[usize]) -> Vec<bool> {
+    let mut kept = vec![true; n];
+    let kept_slice = UnsafeSlice::new(&mut kept);
+    excluded
+        .par_iter()
+        .for_each(|&i| unsafe { kept_slice.write(i, false) });
+    kept
+}
+
+/// The positions in `0..n` not in `excluded`, in increasing order.
+fn complement_indices(n: usize, excluded: &[usize]) -> Vec<usize> {
+    let kept = kept_mask(n, excluded);
+    par_pack_range(n, |i| kept[i].then_some(i))
+}
+
+/// The values of `arr` not at a position in `excluded`, in population order.
+fn complement_values<T: Clone + Send + Sync>(arr: &[T], excluded: &[usize]) -> Vec<T> {
+    let kept = kept_mask(arr.len(), excluded);
+    par_pack_range(arr.len(), |i| kept[i].then(|| arr[i].clone()))
+}
+
+/// Lets a sampler be picked at runtime, e.g. from a config file.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SamplerKind {
+    Naive,
+    SeqPriority,
+    ParPriority,
+    SeqPermutation,
+    FullPermutation,
+    ParPermutation,
+    Reservoir,
+    Replacement,
+    Selection,
+    ParSelection,
+    Hypergeometric,
+    Floyd,
+    ParFloyd,
+    Auto,
+}
+
+impl SamplerKind {
+    pub fn build<T: Clone + Send + Sync>(self, config: SamplerConfig) -> Box<dyn Sampler<T>> {
+        match self {
+            SamplerKind::Naive => Box::new(NaiveSampler::new().with_config(config)),
+            SamplerKind::SeqPriority => Box::new(SeqPrioritySampler::new().with_config(config)),
+            SamplerKind::ParPriority => Box::new(ParPrioritySampler::new().with_config(config)),
+            SamplerKind::SeqPermutation => {
+                Box::new(SeqPermutationSampler::new().with_config(config))
+            }
+            SamplerKind::FullPermutation => {
+                Box::new(FullPermutationSampler::new().with_config(config))
+            }
+            SamplerKind::ParPermutation => Box::new(PermutationSampler::new().with_config(config)),
+            SamplerKind::Reservoir => Box::new(ReservoirSampler::new().with_config(config)),
+            SamplerKind::Replacement => Box::new(ReplacementSampler::new().with_config(config)),
+            SamplerKind::Selection => Box::new(SelectionSampler::new().with_config(config)),
+            SamplerKind::ParSelection => Box::new(ParSelectionSampler::new().with_config(config)),
+            SamplerKind::Hypergeometric => {
+                Box::new(HypergeometricSampler::new().with_config(config))
+            }
+            SamplerKind::Floyd => Box::new(FloydSampler::new().with_config(config)),
+            SamplerKind::ParFloyd => Box::new(ParFloydSampler::new().with_config(config)),
+            SamplerKind::Auto => Box::new(AutoSampler::new().with_config(config)),
+        }
+    }
+}
+
+impl FromStr for SamplerKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "naive" => Ok(SamplerKind::Naive),
+            "seqpriority" => Ok(SamplerKind::SeqPriority),
+            "parpriority" | "priority" => Ok(SamplerKind::ParPriority),
+            "seqpermutation" => Ok(SamplerKind::SeqPermutation),
+            "fullpermutation" => Ok(SamplerKind::FullPermutation),
+            "parpermutation" | "permutation" => Ok(SamplerKind::ParPermutation),
+            "reservoir" => Ok(SamplerKind::Reservoir),
+            "replacement" | "bootstrap" => Ok(SamplerKind::Replacement),
+            "selection" => Ok(SamplerKind::Selection),
+            "parselection" => Ok(SamplerKind::ParSelection),
+            "hypergeometric" => Ok(SamplerKind::Hypergeometric),
+            "floyd" => Ok(SamplerKind::Floyd),
+            "parfloyd" => Ok(SamplerKind::ParFloyd),
+            "auto" => Ok(SamplerKind::Auto),
+            _ => Err(format!("unknown sampler: {}", s)),
+        }
+    }
+}
+
+pub(crate) mod test {
+    /// How often every index of `0..n` came up in `trials` samples of `k`,
+    /// the `t`-th one drawn by `sample(t)`.
+    #[allow(dead_code)]
+    pub(crate) fn inclusion_counts(
+        n: usize,
+        k: usize,
+        trials: u64,
+        sample: impl Fn(u64) -> Vec<usize>,
+    ) -> Vec<usize> {
+        let mut counts = vec![0; n];
+        (0..trials).for_each(|trial| {
+            let indices = sample(trial);
+            assert_eq!(k, indices.len());
+            indices.iter().for_each(|&i| counts[i] += 1);
+        });
+        counts
+    }
+
+    /// Asserts every count is within `tolerance * expected` of `expected`.
+    #[allow(dead_code)]
+    pub(crate) fn assert_uniform(counts: &[usize], expected: f64, tolerance: f64) {
+        assert!(
+            counts
+                .iter()
+                .all(|&c| (c as f64 - expected).abs() < tolerance * expected),
+            "counts {counts:?} stray from {expected}"
+        );
+    }
+
+    #[test]
+    fn boxed_samplers_from_config() {
+        use super::{Sampler, SamplerConfig, SamplerKind};
+
+        let xs: Vec<i32> = (0..10_000).collect();
+        let samplers: Vec<Box<dyn Sampler<i32>>> = [
+            "naive",
+            "seqpriority",
+            "parpriority",
+            "seqpermutation",
+            "fullpermutation",
+            "parpermutation",
+            "reservoir",
+            "replacement",
+            "selection",
+            "parselection",
+            "hypergeometric",
+            "floyd",
+            "parfloyd",
+            "auto",
+        ]
+        .iter()
+        .map(|name| {
+            name.parse::<SamplerKind>()
+                .unwrap()
+                .build(SamplerConfig::new().seed(3))
+        })
+        .collect();
+
+        samplers.iter().for_each(|sampler| {
+            let first = sampler.sample(&xs, 100).unwrap();
+            assert_eq!(first.len(), 100);
+            assert_eq!(first, sampler.sample(&xs, 100).unwrap());
+        });
+    }
+
+    #[test]
+    fn sampler_uses_its_thread_pool() {
+        use super::{Sampler, SamplerConfig};
+        use crate::samplers::perm_sampler::PermutationSampler;
+        use std::sync::Arc;
+
+        let pool = Arc::new(
+            rayon::ThreadPoolBuilder::new()
+                .num_threads(2)
+                .build()
+                .unwrap(),
+        );
+        let xs: Vec<i32> = (0..10_000).collect();
+        let sampler =
+            PermutationSampler::new().with_config(SamplerConfig::new().seed(5).thread_pool(pool));
+        let unpooled = PermutationSampler::new().with_config(SamplerConfig::new().seed(5));
+        assert_eq!(sampler.sample(&xs, 1_000), unpooled.sample(&xs, 1_000));
+    }
+
+    #[test]
+    fn samplers_reject_bad_k() {
+        use super::{Sampler, SamplerConfig, SamplerKind};
+        use crate::samplers::sampl_error::SamplingError;
+
+        let xs: Vec<i32> = (0..100).collect();
+        let empty: Vec<i32> = vec![];
+        [
+            SamplerKind::Naive,
+            SamplerKind::SeqPriority,
+            SamplerKind::ParPriority,
+            SamplerKind::SeqPermutation,
+            SamplerKind::FullPermutation,
+            SamplerKind::ParPermutation,
+            SamplerKind::Reservoir,
+            SamplerKind::Selection,
+            SamplerKind::ParSelection,
+            SamplerKind::Hypergeometric,
+            SamplerKind::Floyd,
+            SamplerKind::ParFloyd,
+            SamplerKind::Auto,
+        ]
+        .iter()
+        .for_each(|kind| {
+            let sampler: Box<dyn Sampler<i32>> = kind.build(SamplerConfig::new());
+            assert_eq!(
+                sampler.sample(&xs, 101),
+                Err(SamplingError::KGreaterThanN { k: 101, n: 100 })
+            );
+            assert_eq!(
+                sampler.sample(&empty, 1),
+                Err(SamplingError::EmptyPopulation)
+            );
+            assert_eq!(sampler.sample(&empty, 0), Ok(vec![]));
+            assert_eq!(sampler.sample(&xs, 0), Ok(vec![]));
+            assert_eq!(sampler.sample(&xs, 100).unwrap().len(), 100);
+        });
+    }
+
+    #[test]
+    fn output_orders_keep_the_sample() {
+        use super::{OutputOrder, Sampler, SamplerConfig, SamplerKind};
+        use std::fmt::Debug;
+
+        /// Far wider than `usize`, which `AutoSampler` costs differently.
+        #[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+        struct Wide {
+            id: usize,
+            payload: [u8; 256],
+        }
+
+        fn check<T: Clone + Ord + Debug + Send + Sync>(kind: SamplerKind, xs: &[T]) {
+            let samples: Vec<Vec<T>> = [
+                OutputOrder::Any,
+                OutputOrder::Random,
+                OutputOrder::Stable,
+                OutputOrder::SortedByIndex,
+            ]
+            .iter()
+            .map(|&order| {
+                let sampler: Box<dyn Sampler<T>> =
+                    kind.build(SamplerConfig::new().seed(11).order(order));
+                sampler.sample(xs, 500).unwrap()
+            })
+            .collect();
+
+            let mut sorted = samples[0].clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, samples[2], "{:?}", kind);
+            assert_eq!(sorted, samples[3], "{:?}", kind);
+            let mut shuffled = samples[1].clone();
+            assert_ne!(sorted, shuffled, "{:?}", kind);
+            shuffled.sort_unstable();
+            assert_eq!(sorted, shuffled, "{:?}", kind);
+        }
+
+        let xs: Vec<usize> = (0..10_000).collect();
+        let wide: Vec<Wide> = xs
+            .iter()
+            .map(|&id| Wide {
+                id,
+                payload: [id as u8; 256],
+            })
+            .collect();
+        [
+            SamplerKind::Naive,
+            SamplerKind::SeqPriority,
+            SamplerKind::ParPriority,
+            SamplerKind::SeqPermutation,
+            SamplerKind::FullPermutation,
+            SamplerKind::ParPermutation,
+            SamplerKind::Reservoir,
+            SamplerKind::Replacement,
+            SamplerKind::Selection,
+            SamplerKind::ParSelection,
+            SamplerKind::Hypergeometric,
+            SamplerKind::Floyd,
+            SamplerKind::ParFloyd,
+            SamplerKind::Auto,
+        ]
+        .into_iter()
+        .for_each(|kind| {
+            check(kind, &xs);
+            check(kind, &wide);
+        });
+    }
+
+    #[test]
+    fn index_samples_sorted_by_index() {
+        use super::{IndexSampler, OutputOrder, SamplerConfig};
+        use crate::samplers::{
+            perm_sampler::PermutationSampler, reservoir_sampler::ReservoirSampler,
+            sampl_error::SamplingError,
+        };
+
+        let config = SamplerConfig::new()
+            .seed(2)
+            .order(OutputOrder::SortedByIndex);
+        let indices = PermutationSampler::new()
+            .with_config(config.clone())
+            .sample_indices(100_000, 5_000)
+            .unwrap();
+        assert!(indices.windows(2).all(|w| w[0] < w[1]));
+
+        let reservoir = ReservoirSampler::new().with_config(config);
+        let streamed = reservoir.sample_iter(0..100_000, 5_000).unwrap();
+        assert!(streamed.windows(2).all(|w| w[0] < w[1]));
+        assert_eq!(
+            reservoir.par_sample_iter(0..100_000, 5_000),
+            Err(SamplingError::UnsupportedOrder(OutputOrder::SortedByIndex))
+        );
+    }
+
+    #[test]
+    fn complement_mode_keeps_the_rest() {
+        use super::{Complement, IndexSampler, Sampler, SamplerConfig};
+        use crate::samplers::{
+            naive_sampler::NaiveSampler, perm_sampler::PermutationSampler,
+            priority_sampler::ParPrioritySampler,
+        };
+
+        fn check<S: IndexSampler + Sampler<usize>>(sampler: S) {
+            let n = 100_000;
+            let k = n * 9 / 10;
+            let xs: Vec<usize> = (0..n).collect();
+            let indices = sampler.sample_indices(n, k).unwrap();
+            assert_eq!(k, indices.len());
+            assert!(indices.windows(2).all(|w| w[0] < w[1]));
+            assert!(indices[k - 1] < n);
+            assert_eq!(indices, sampler.sample(&xs, k).unwrap());
+        }
+
+        let config = SamplerConfig::new().seed(12);
+        check(
+            NaiveSampler::new()
+                .with_config(config.clone())
+                .complement(true),
+        );
+        check(
+            ParPrioritySampler::new()
+                .with_config(config.clone())
+                .complement(true),
+        );
+        check(
+            PermutationSampler::new()
+                .with_config(config)
+                .complement(true),
+        );
+    }
+
+    #[test]
+    fn config_builds_thread_pool() {
+        use super::SamplerConfig;
+
+        assert!(SamplerConfig::new().num_threads(2).is_ok());
+    }
+
+    #[test]
+    fn index_samplers_give_distinct_indices() {
+        use super::{IndexSampler, SamplerConfig};
+        use crate::samplers::{
+            naive_sampler::NaiveSampler,
+            perm_sampler::{PermutationSampler, SeqPermutationSampler},
+            priority_sampler::{ParPrioritySampler, SeqPrioritySampler},
+        };
+        use std::collections::HashSet;
+
+        let n = 100_000;
+        let k = 1_000;
+        let config = SamplerConfig::new().seed(8);
+        let samples = vec![
+            NaiveSampler::new()
+                .with_config(config.clone())
+                .sample_indices(n, k),
+            SeqPermutationSampler::new()
+                .with_config(config.clone())
+                .sample_indices(n, k),
+            PermutationSampler::new()
+                .with_config(config.clone())
+                .sample_indices(n, k),
+            SeqPrioritySampler::new()
+                .with_config(config.clone())
+                .sample_indices(n, k),
+            ParPrioritySampler::new()
+                .with_config(config)
+                .sample_indices(n, k),
+        ];
+        samples.into_iter().for_each(|indices| {
+            let indices = indices.unwrap();
+            assert_eq!(k, indices.len());
+            assert!(indices.iter().all(|&i| i < n));
+            assert_eq!(k, indices.iter().collect::<HashSet<_>>().len());
+        });
+    }
+
+    #[test]
+    fn refs_of_non_clone_values() {
+        use super::IndexSampler;
+        use crate::samplers::perm_sampler::PermutationSampler;
+
+        struct Record {
+            id: usize,
+        }
+
+        let records: Vec<Record> = (0..1_000).map(|id| Record { id }).collect();
+        let samples = PermutationSampler::new().sample_refs(&records, 10).unwrap();
+        assert_eq!(10, samples.len());
+        assert!(samples.iter().all(|r| r.id < 1_000));
+    }
 }
@@ -0,0 +1,291 @@
+use rand::{Rng, SeedableRng};
+
+use crate::samplers::{
+    hypergeometric_sampler::split_sample,
+    sampl_error::SamplingError,
+    sampl_interface::{Configurable, IndexSampler, Sampler, SamplerConfig},
+};
+use crate::utils::rng::{open_unit, SplitMix64};
+
+/// Algorithm D hands over to Method A once `n <= ALPHA_INV * k`, where
+/// scanning the skips one by one is cheaper than rejection.
+const ALPHA_INV: usize = 13;
+/// Ranges that should yield at most this many indices are sampled
+/// sequentially instead of being split further.
+const LEAF_SIZE: usize = 1 << 12;
+
+/// Vitter's Method A: fills `out` with `out.len()` increasing indices from
+/// `base..base + n` by drawing every skip length by inversion.
+fn method_a(out: &mut [usize], base: usize, n: usize, rng: &mut SplitMix64) {
+    let k = out.len();
+    let mut top = (n - k) as f64;
+    let mut n_real = n as f64;
+    let mut pos = base;
+
+    for slot in out.iter_mut().take(k - 1) {
+        let v = open_unit(rng);
+        let mut quot = top / n_real;
+        while quot > v {
+            pos += 1;
+            top -= 1.0;
+            n_real -= 1.0;
+            quot *= top / n_real;
+        }
+        *slot = pos;
+        pos += 1;
+        n_real -= 1.0;
+    }
+    out[k - 1] = pos + (n_real * rng.gen::<f64>()) as usize;
+}
+
+/// Vitter's Algorithm D: fills `out` with `out.len()` increasing indices from
+/// `base..base + n` in O(k) expected time, drawing the skip before every
+/// selected index by rejection from a continuous approximation.
+fn method_d(out: &mut [usize], base: usize, n: usize, rng: &mut SplitMix64) {
+    let mut k = out.len();
+    if k == 0 {
+        return;
+    }
+
+    let (mut big_n, mut big_n_real) = (n, n as f64);
+    let mut k_real = k as f64;
+    let mut k_inv = 1.0 / k_real;
+    let mut v_prime = (open_unit(rng).ln() * k_inv).exp();
+    let (mut qu1, mut qu1_real) = (n - k + 1, (n - k + 1) as f64);
+    let mut pos = base;
+    let mut filled = 0;
+
+    while k > 1 && ALPHA_INV * k < big_n {
+        let k_min1_inv = 1.0 / (k_real - 1.0);
+        let (skip, skip_real) = loop {
+            // D2: a candidate skip from the continuous approximation
+            let (x, skip) = loop {
+                let x = big_n_real * (1.0 - v_prime);
+                let skip = x as usize;
+                if skip < qu1 {
+                    break (x, skip);
+                }
+                v_prime = (open_unit(rng).ln() * k_inv).exp();
+            };
+            let skip_real = skip as f64;
+
+            // D3: quick acceptance test
+            let u = open_unit(rng);
+            let y1 = ((u * big_n_real / qu1_real).ln() * k_min1_inv).exp();
+            v_prime = y1 * (1.0 - x / big_n_real) * (qu1_real / (qu1_real - skip_real));
+            if v_prime <= 1.0 {
+                break (skip, skip_real);
+            }
+
+            // D4: exact acceptance test
+            let mut y2 = 1.0;
+            let mut top = big_n_real - 1.0;
+            let (mut bottom, limit) = if k - 1 > skip {
+                (big_n_real - k_real, big_n - skip)
+            } else {
+                (big_n_real - skip_real - 1.0, qu1)
+            };
+            (limit..big_n).for_each(|_| {
+                y2 *= top / bottom;
+                top -= 1.0;
+                bottom -= 1.0;
+            });
+            if big_n_real / (big_n_real - x) >= y1 * (y2.ln() * k_min1_inv).exp() {
+                v_prime = (open_unit(rng).ln() * k_min1_inv).exp();
+                break (skip, skip_real);
+            }
+            v_prime = (open_unit(rng).ln() * k_inv).exp();
+        };
+
+        // D5: skip, then select
+        pos += skip;
+        out[filled] = pos;
+        filled += 1;
+        pos += 1;
+        big_n -= skip + 1;
+        big_n_real -= skip_real + 1.0;
+        k -= 1;
+        k_real -= 1.0;
+        k_inv = k_min1_inv;
+        qu1 -= skip;
+        qu1_real -= skip_real;
+    }
+
+    if k > 1 {
+        method_a(&mut out[filled..], pos, big_n, rng);
+    } else {
+        out[filled] = pos + (big_n_real * v_prime) as usize;
+    }
+}
+
+/// Sequential sampling by skips (Vitter's Algorithm D): the indices come out
+/// in increasing order, using O(k) memory and O(k) expected time.
+#[derive(Clone, Default)]
+pub struct SelectionSampler {
+    config: SamplerConfig,
+}
+
+impl SelectionSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+impl Configurable for SelectionSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for SelectionSampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        let mut indices = vec![0; k];
+        method_d(&mut indices, 0, n, &mut SplitMix64::seed_from_u64(seed));
+        Ok(indices)
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for SelectionSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_in_pool(arr.len(), k, seed)
+    }
+}
+
+/// Parallel `SelectionSampler`: hypergeometric draws split the index range
+/// (and the sample size) into blocks, which then run Algorithm D
+/// independently. Also in increasing order, but a different sample than the
+/// sequential one for the same seed.
+#[derive(Clone, Default)]
+pub struct ParSelectionSampler {
+    config: SamplerConfig,
+}
+
+impl ParSelectionSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+}
+
+impl Configurable for ParSelectionSampler {
+    fn config(&self) -> &SamplerConfig {
+        &self.config
+    }
+}
+
+impl IndexSampler for ParSelectionSampler {
+    fn sample_indices_in_pool(
+        &self,
+        n: usize,
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        let mut indices = vec![0; k];
+        split_sample(&mut indices, 0, n, seed, 1, LEAF_SIZE, &method_d);
+        Ok(indices)
+    }
+}
+
+impl<T: Clone + Sized + Send + Sync> Sampler<T> for ParSelectionSampler {
+    fn sample_positions_in_pool(
+        &self,
+        arr: &[T],
+        k: usize,
+        seed: u64,
+    ) -> Result<Vec<usize>, SamplingError> {
+        self.sample_indices_in_pool(arr.len(), k, seed)
+    }
+}
+
+mod test {
+    #[test]
+    fn selection_is_uniform() {
+        use super::{method_d, SplitMix64};
+        use rand::SeedableRng;
+
+        // (20, 1) and (1_000, 10) stay in Algorithm D, (20, 5) goes to Method A
+        [(20, 1), (20, 5), (1_000, 10)].iter().for_each(|&(n, k)| {
+            let trials = 200_000 / k;
+            let mut counts = vec![0; n];
+            let mut rng = SplitMix64::seed_from_u64(n as u64);
+            (0..trials).for_each(|_| {
+                let mut out = vec![0; k];
+                method_d(&mut out, 0, n, &mut rng);
+                assert!(out.windows(2).all(|w| w[0] < w[1]));
+                assert!(out[k - 1] < n);
+                out.iter().for_each(|&i| counts[i] += 1);
+            });
+
+            let expected = (trials * k / n) as f64;
+            let tolerance = 5.0 * expected.sqrt();
+            assert!(counts
+                .iter()
+                .all(|&c| (c as f64 - expected).abs() < tolerance));
+        });
+    }
+
+    #[test]
+    fn selection_gives_increasing_indices() {
+        use super::{ParSelectionSampler, SelectionSampler};
+        use crate::samplers::sampl_interface::{IndexSampler, SamplerConfig};
+
+        let n = 10_000_000;
+        [1, 1_000, 100_000, 5_000_000, n].iter().for_each(|&k| {
+            let samples = [
+                SelectionSampler::new().sample_indices(n, k).unwrap(),
+                ParSelectionSampler::new().sample_indices(n, k).unwrap(),
+            ];
+            samples.iter().for_each(|indices| {
+                assert_eq!(k, indices.len());
+                assert!(indices.windows(2).all(|w| w[0] < w[1]));
+                assert!(indices[k - 1] < n);
+            });
+        });
+
+        let par = [1, 4].map(|threads| {
+            ParSelectionSampler::new()
+                .with_config(SamplerConfig::new().seed(6).num_threads(threads).unwrap())
+                .sample_indices(n, 100_000)
+                .unwrap()
+        });
+        assert_eq!(par[0], par[1]);
+    }
+
+    #[test]
+    fn par_selection_is_uniform() {
+        use super::ParSelectionSampler;
+        use crate::samplers::sampl_interface::{test::assert_uniform, IndexSampler};
+
+        // enough per sample to go through several hypergeometric splits
+        let (n, k, trials) = (100_000, 20_000, 50);
+        let mut halves = [0; 2];
+        (0..trials).for_each(|seed| {
+            ParSelectionSampler::new()
+                .sample_indices_seeded(n, k, seed)
+                .unwrap()
+                .iter()
+                .for_each(|&i| halves[i * 2 / n] += 1);
+        });
+
+        assert_uniform(&halves, (trials as usize * k / 2) as f64, 0.01);
+    }
+}
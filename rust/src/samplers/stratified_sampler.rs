@@ -0,0 +1,329 @@
+use std::collections::BTreeSet;
+
+use rand::RngCore;
+use rayon::{
+    iter::{IntoParallelIterator, IntoParallelRefIterator, ParallelIterator},
+    slice::{ParallelSlice, ParallelSliceMut},
+};
+
+use crate::samplers::{
+    perm_sampler::PermutationSampler,
+    priority_sampler::ParPrioritySampler,
+    sampl_error::{check_k, SamplingError},
+    sampl_interface::{gather, IndexSampler, SamplerConfig},
+};
+use crate::utils::{counting_sort::par_group_by_id, rng::index_rng};
+
+const DEDUP_BLOCK_SIZE: usize = 1 << 13;
+
+/// The sampler run inside every stratum.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StratumAlgorithm {
+    #[default]
+    Permutation,
+    Priority,
+}
+
+/// How many items to take from each stratum.
+#[derive(Clone, Copy, Debug)]
+pub enum Allocation<'a> {
+    /// The same number from every stratum, or all of it if it is smaller.
+    Fixed(usize),
+    /// This many in total, split proportionally to the stratum sizes.
+    Proportional(usize),
+    /// `k` in total, split proportionally to `N_h * S_h`, where `N_h` is the
+    /// size of stratum `h` and `S_h` the standard deviation of `values`
+    /// (one per population item) within it.
+    Neyman { k: usize, values: &'a [f64] },
+}
+
+/// Splits `total` proportionally to `weights` without going over `caps`,
+/// rounding by largest remainder. Strata whose share exceeds their cap are
+/// taken whole and the rest is split again among the others.
+fn apportion(total: usize, weights: &[f64], caps: &[usize]) -> Vec<usize> {
+    let mut alloc = vec![0; caps.len()];
+    let mut open: Vec<usize> = (0..caps.len()).filter(|&h| caps[h] > 0).collect();
+    let mut left = total;
+
+    while left > 0 && !open.is_empty() {
+        let weight_sum: f64 = open.iter().map(|&h| weights[h]).sum();
+        let share = |h: usize| {
+            if weight_sum > 0.0 {
+                left as f64 * weights[h] / weight_sum
+            } else {
+                left as f64 / open.len() as f64
+            }
+        };
+        let (full, rest): (Vec<usize>, Vec<usize>) =
+            open.iter().partition(|&&h| share(h) >= caps[h] as f64);
+
+        if full.is_empty() {
+            let mut shares: Vec<(usize, f64)> = open.iter().map(|&h| (h, share(h))).collect();
+            shares
+                .iter()
+                .for_each(|&(h, s)| alloc[h] = s.floor() as usize);
+            let given: usize = open.iter().map(|&h| alloc[h]).sum();
+            shares.sort_by(|a, b| (b.1 - b.1.floor()).total_cmp(&(a.1 - a.1.floor())));
+            shares
+                .iter()
+                .take(left.saturating_sub(given))
+                .for_each(|&(h, _)| alloc[h] += 1);
+            break;
+        }
+
+        full.iter().for_each(|&h| {
+            alloc[h] = caps[h];
+            left = left.saturating_sub(caps[h]);
+        });
+        open = rest;
+    }
+
+    alloc
+}
+
+fn std_dev(values: &[f64], members: &[usize]) -> f64 {
+    if members.len() < 2 {
+        return 0.0;
+    }
+    let n = members.len() as f64;
+    let mean = members.iter().map(|&i| values[i]).sum::<f64>() / n;
+    let var = members
+        .iter()
+        .map(|&i| (values[i] - mean).powi(2))
+        .sum::<f64>()
+        / (n - 1.0);
+    var.sqrt()
+}
+
+/// Samples every stratum (the items sharing a key) separately. Strata come
+/// back sorted by key, each in the configured output order.
+#[derive(Clone, Default)]
+pub struct StratifiedSampler {
+    config: SamplerConfig,
+    algorithm: StratumAlgorithm,
+}
+
+impl StratifiedSampler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_config(mut self, config: SamplerConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn algorithm(mut self, algorithm: StratumAlgorithm) -> Self {
+        self.algorithm = algorithm;
+        self
+    }
+
+    fn allocate(
+        &self,
+        allocation: Allocation,
+        n: usize,
+        starts: &[usize],
+        order: &[usize],
+    ) -> Result<Vec<usize>, SamplingError> {
+        let sizes: Vec<usize> = starts.windows(2).map(|w| w[1] - w[0]).collect();
+        match allocation {
+            Allocation::Fixed(k) => Ok(sizes.iter().map(|&size| size.min(k)).collect()),
+            Allocation::Proportional(k) => {
+                check_k(n, k)?;
+                let weights: Vec<f64> = sizes.iter().map(|&size| size as f64).collect();
+                Ok(apportion(k, &weights, &sizes))
+            }
+            Allocation::Neyman { k, values } => {
+                check_k(n, k)?;
+                if values.len() != n {
+                    return Err(SamplingError::LengthMismatch {
+                        population: n,
+                        weights: values.len(),
+                    });
+                }
+                if !values.par_iter().all(|v| v.is_finite()) {
+                    return Err(SamplingError::InvalidWeights);
+                }
+                let weights: Vec<f64> = starts
+                    .par_windows(2)
+                    .map(|w| {
+                        let members = &order[w[0]..w[1]];
+                        members.len() as f64 * std_dev(values, members)
+                    })
+                    .collect();
+                if weights.iter().all(|&w| w == 0.0) {
+                    // no variability anywhere: Neyman degenerates to proportional
+                    let sizes_f: Vec<f64> = sizes.iter().map(|&size| size as f64).collect();
+                    return Ok(apportion(k, &sizes_f, &sizes));
+                }
+                Ok(apportion(k, &weights, &sizes))
+            }
+        }
+    }
+
+    /// Per stratum, the key and the sampled indices into `arr`.
+    pub fn sample_indices_by<T, K, F>(
+        &self,
+        arr: &[T],
+        key: F,
+        allocation: Allocation,
+    ) -> Result<Vec<(K, Vec<usize>)>, SamplingError>
+    where
+        T: Sync,
+        K: Ord + Clone + Send + Sync,
+        F: Fn(&T) -> K + Sync + Send,
+    {
+        let seed = self.config.resolve_seed();
+        self.config.install(|| {
+            let keys: Vec<K> = arr.par_iter().map(&key).collect();
+            // dedupe every block first, so only distinct keys get sorted
+            let mut strata: Vec<K> = keys
+                .par_chunks(DEDUP_BLOCK_SIZE)
+                .flat_map_iter(|block| block.iter().cloned().collect::<BTreeSet<K>>())
+                .collect();
+            strata.par_sort_unstable();
+            strata.dedup();
+
+            let ids: Vec<usize> = keys
+                .par_iter()
+                .map(|key| strata.binary_search(key).unwrap())
+                .collect();
+            let (starts, order) = par_group_by_id(&ids, strata.len());
+            let alloc = self.allocate(allocation, arr.len(), &starts, &order)?;
+
+            let samples = (0..strata.len())
+                .into_par_iter()
+                .map(|h| {
+                    let members = &order[starts[h]..starts[h + 1]];
+                    let stratum_seed = index_rng(seed, h).next_u64();
+                    let config = SamplerConfig::new().order(self.config.get_order());
+                    let local = match self.algorithm {
+                        StratumAlgorithm::Permutation => PermutationSampler::new()
+                            .with_config(config)
+                            .sample_indices_seeded(members.len(), alloc[h], stratum_seed),
+                        StratumAlgorithm::Priority => ParPrioritySampler::new()
+                            .with_config(config)
+                            .sample_indices_seeded(members.len(), alloc[h], stratum_seed),
+                    }?;
+                    Ok(local.iter().map(|&i| members[i]).collect())
+                })
+                .collect::<Result<Vec<Vec<usize>>, SamplingError>>()?;
+
+            Ok(strata.into_iter().zip(samples).collect())
+        })
+    }
+
+    pub fn sample_by<T, K, F>(
+        &self,
+        arr: &[T],
+        key: F,
+        allocation: Allocation,
+    ) -> Result<Vec<(K, Vec<T>)>, SamplingError>
+    where
+        T: Clone + Send + Sync,
+        K: Ord + Clone + Send + Sync,
+        F: Fn(&T) -> K + Sync + Send,
+    {
+        let strata = self.sample_indices_by(arr, key, allocation)?;
+        Ok(self.config.install(|| {
+            strata
+                .into_par_iter()
+                .map(|(key, indices)| (key, gather(arr, &indices)))
+                .collect()
+        }))
+    }
+}
+
+mod test {
+    #[test]
+    fn fixed_allocation_per_stratum() {
+        use super::{Allocation, StratifiedSampler, StratumAlgorithm};
+
+        let population: Vec<usize> = (0..10_000).collect();
+        [StratumAlgorithm::Permutation, StratumAlgorithm::Priority]
+            .iter()
+            .for_each(|&algorithm| {
+                let strata = StratifiedSampler::new()
+                    .algorithm(algorithm)
+                    .sample_by(
+                        &population,
+                        |&x| if x < 30 { 0 } else { x % 3 + 1 },
+                        Allocation::Fixed(50),
+                    )
+                    .unwrap();
+
+                assert_eq!(
+                    vec![0, 1, 2, 3],
+                    strata.iter().map(|(k, _)| *k).collect::<Vec<_>>()
+                );
+                assert_eq!(30, strata[0].1.len());
+                strata.iter().skip(1).for_each(|(key, samples)| {
+                    assert_eq!(50, samples.len());
+                    assert!(samples.iter().all(|&x| x >= 30 && x % 3 + 1 == *key));
+                });
+            });
+    }
+
+    #[test]
+    fn proportional_allocation_sums_to_k() {
+        use super::{Allocation, StratifiedSampler};
+        use crate::samplers::sampl_error::SamplingError;
+
+        let population: Vec<usize> = (0..10_000).collect();
+        let key = |&x: &usize| {
+            if x < 1_000 {
+                'a'
+            } else if x < 4_000 {
+                'b'
+            } else {
+                'c'
+            }
+        };
+        let strata = StratifiedSampler::new()
+            .sample_indices_by(&population, key, Allocation::Proportional(1_001))
+            .unwrap();
+        let sizes: Vec<usize> = strata.iter().map(|(_, s)| s.len()).collect();
+
+        assert_eq!(1_001, sizes.iter().sum::<usize>());
+        assert!((100..=101).contains(&sizes[0]));
+        assert!((300..=301).contains(&sizes[1]));
+        assert!((600..=601).contains(&sizes[2]));
+        assert_eq!(
+            StratifiedSampler::new().sample_indices_by(
+                &population,
+                key,
+                Allocation::Proportional(10_001)
+            ),
+            Err(SamplingError::KGreaterThanN {
+                k: 10_001,
+                n: 10_000
+            })
+        );
+    }
+
+    #[test]
+    fn neyman_follows_spread() {
+        use super::{Allocation, StratifiedSampler};
+        use crate::samplers::sampl_interface::SamplerConfig;
+
+        let population: Vec<usize> = (0..2_000).collect();
+        let values: Vec<f64> = population
+            .iter()
+            .map(|&x| if x % 2 == 0 { 1.0 } else { x as f64 })
+            .collect();
+        let strata = StratifiedSampler::new()
+            .with_config(SamplerConfig::new().seed(5))
+            .sample_indices_by(
+                &population,
+                |&x| x % 2,
+                Allocation::Neyman {
+                    k: 100,
+                    values: &values,
+                },
+            )
+            .unwrap();
+
+        assert_eq!(0, strata[0].1.len());
+        assert_eq!(100, strata[1].1.len());
+    }
+}
@@ -0,0 +1,88 @@
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelIterator, ParallelIterator},
+    slice::ParallelSlice,
+};
+
+use crate::utils::{cwslice::UnsafeSlice, prefix_scan::par_scan};
+
+const BLOCK_SIZE: usize = 1 << 14;
+
+/// Stable parallel counting sort of the positions `0..ids.len()` by `ids[i]`,
+/// where every id is below `num_groups`. Returns `(starts, order)`: the
+/// positions with id `g` are `order[starts[g]..starts[g + 1]]`, increasing.
+///
+/// Each block of the input builds a histogram, the histograms are laid out
+/// group-major and scanned, so every (group, block) pair knows where to write.
+pub fn par_group_by_id(ids: &[usize], num_groups: usize) -> (Vec<usize>, Vec<usize>) {
+    let n = ids.len();
+    if n == 0 {
+        return (vec![0; num_groups + 1], vec![]);
+    }
+
+    // fewer, larger blocks when there are many groups, to bound the histograms
+    let max_blocks = (4 * n / num_groups.max(1)).max(1);
+    let num_blocks = n.div_ceil(BLOCK_SIZE).min(max_blocks);
+    let block_len = n.div_ceil(num_blocks);
+    let num_blocks = n.div_ceil(block_len);
+
+    let mut counts = vec![0; num_groups * num_blocks];
+    let counts_slice = UnsafeSlice::new(&mut counts);
+    ids.par_chunks(block_len)
+        .enumerate()
+        .for_each(|(b, block)| {
+            let mut histogram = vec![0; num_groups];
+            block.iter().for_each(|&id| histogram[id] += 1);
+            histogram.iter().enumerate().for_each(|(g, &count)| unsafe {
+                counts_slice.write(g * num_blocks + b, count);
+            });
+        });
+    let (_, offsets) = par_scan(&counts, 0, |a, b| a + b);
+
+    let mut order = vec![0; n];
+    let order_slice = UnsafeSlice::new(&mut order);
+    ids.par_chunks(block_len)
+        .enumerate()
+        .for_each(|(b, block)| {
+            let mut cursors: Vec<usize> = (0..num_groups)
+                .map(|g| offsets[g * num_blocks + b])
+                .collect();
+            block.iter().enumerate().for_each(|(j, &id)| {
+                unsafe {
+                    order_slice.write(cursors[id], b * block_len + j);
+                }
+                cursors[id] += 1;
+            });
+        });
+
+    let starts = (0..=num_groups)
+        .into_par_iter()
+        .map(|g| {
+            if g == num_groups {
+                n
+            } else {
+                offsets[g * num_blocks]
+            }
+        })
+        .collect();
+    (starts, order)
+}
+
+mod test {
+    #[test]
+    fn groups_are_stable() {
+        use super::par_group_by_id;
+
+        let n = 100_000;
+        [3, 1_000, 100_000].iter().for_each(|&num_groups| {
+            let ids: Vec<usize> = (0..n).map(|i| (i * 7919) % num_groups).collect();
+            let (starts, order) = par_group_by_id(&ids, num_groups);
+
+            let mut expected: Vec<usize> = (0..n).collect();
+            expected.sort_by_key(|&i| ids[i]);
+            assert_eq!(expected, order);
+            (0..num_groups).for_each(|g| {
+                assert!(order[starts[g]..starts[g + 1]].iter().all(|&i| ids[i] == g));
+            });
+        });
+    }
+}
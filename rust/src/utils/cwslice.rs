@@ -17,21 +17,24 @@ impl<'a, T> UnsafeSlice<'a, T> {
         }
     }
 
-    /// SAFETY: It is UB if two threads write to the same index without
+    /// # Safety
+    /// It is UB if two threads write to the same index without
     /// synchronization.
     pub unsafe fn write(&self, i: usize, value: T) {
         let ptr = self.slice[i].get();
         *ptr = value;
     }
 
+    /// # Safety
+    /// It is UB to read an index that another thread is writing to.
     #[allow(dead_code)]
     pub unsafe fn read(&self, i: usize) -> &T {
         let ptr = self.slice[i].get();
         &*ptr
     }
-}
 
-impl<'a, T: Clone + Sized + Send + Sync> UnsafeSlice<'a, T> {
+    /// # Safety
+    /// It is UB if another thread touches `loc_a` or `loc_b` during the swap.
     pub unsafe fn swap(&self, loc_a: usize, loc_b: usize) {
         let ptr_a = self.slice[loc_a].get();
         let ptr_b = self.slice[loc_b].get();
@@ -0,0 +1,242 @@
+use rayon::iter::{
+    IntoParallelIterator, IntoParallelRefIterator, ParallelExtend, ParallelIterator,
+};
+
+use crate::utils::pack::par_pack_range;
+
+/// Default divisor of `RoundPolicy::Fraction`.
+pub const PREFIX_DIVISOR: usize = 100;
+
+/// How many of the remaining indices a round of deterministic reservations
+/// takes on. Bigger rounds mean fewer rounds but more conflicts.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RoundPolicy {
+    /// Always the next `size` indices.
+    Fixed(usize),
+    /// `max(remaining / divisor, divisor)` indices.
+    Fraction(usize),
+    /// Starts at `initial` and doubles after every round in which at most
+    /// `max_conflicts` of the indices failed to commit.
+    Geometric { initial: usize, max_conflicts: f64 },
+    /// Starts at `initial` and scales every round by how far its share of
+    /// failed commits was from `target`, by at most a factor of two.
+    Adaptive { initial: usize, target: f64 },
+}
+
+impl Default for RoundPolicy {
+    fn default() -> Self {
+        RoundPolicy::Fraction(PREFIX_DIVISOR)
+    }
+}
+
+impl RoundPolicy {
+    pub fn first_size(&self, remaining: usize) -> usize {
+        let size = match *self {
+            RoundPolicy::Fixed(size) => size,
+            RoundPolicy::Fraction(divisor) => {
+                let divisor = divisor.max(1);
+                (remaining / divisor).max(divisor)
+            }
+            RoundPolicy::Geometric { initial, .. } | RoundPolicy::Adaptive { initial, .. } => {
+                initial
+            }
+        };
+        size.max(1)
+    }
+
+    /// Size of the round after one of `last` indices with `failed` conflicts,
+    /// with `remaining` indices left.
+    pub fn next_size(&self, last: usize, failed: usize, remaining: usize) -> usize {
+        let failed_ratio = failed as f64 / last as f64;
+        let size = match *self {
+            RoundPolicy::Fixed(_) | RoundPolicy::Fraction(_) => self.first_size(remaining),
+            RoundPolicy::Geometric { max_conflicts, .. } if failed_ratio <= max_conflicts => {
+                2 * last
+            }
+            RoundPolicy::Geometric { .. } => last,
+            RoundPolicy::Adaptive { target, .. } => {
+                let scale = if failed == 0 {
+                    2.0
+                } else {
+                    (target / failed_ratio).clamp(0.5, 2.0)
+                };
+                (last as f64 * scale) as usize
+            }
+        };
+        size.max(1)
+    }
+}
+
+/// What every round of a deterministic reservations run did.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RoundStats {
+    prefix_sizes: Vec<usize>,
+    conflicts: Vec<usize>,
+}
+
+impl RoundStats {
+    pub fn record(&mut self, prefix_size: usize, conflicts: usize) {
+        self.prefix_sizes.push(prefix_size);
+        self.conflicts.push(conflicts);
+    }
+
+    pub fn rounds(&self) -> usize {
+        self.prefix_sizes.len()
+    }
+
+    /// Indices taken on in every round.
+    pub fn prefix_sizes(&self) -> &[usize] {
+        &self.prefix_sizes
+    }
+
+    /// Indices that failed to commit in every round.
+    pub fn conflicts(&self) -> &[usize] {
+        &self.conflicts
+    }
+}
+
+/// A loop of `n` steps that must appear to run in order, but mostly do not
+/// interfere with each other. Step `i` reserves what it needs, keeping the
+/// reservation of the smallest step when several compete, and then commits
+/// only if it won everything it reserved. Whatever the rounds, the result is
+/// that of running the steps sequentially.
+pub trait ReserveCommit: Sync {
+    /// Called before a round of `round_len` steps.
+    fn start_round(&mut self, _round_len: usize) {}
+
+    fn reserve(&self, i: usize);
+
+    /// Runs step `i` if nothing before it in the round stands in the way;
+    /// false sends it to a later round.
+    fn commit(&self, i: usize) -> bool;
+
+    /// Called after every round with the steps that took part in it, e.g. to
+    /// clear their reservations.
+    fn finish_round(&mut self, _round: &[usize]) {}
+}
+
+/// The deterministic reservations driver (`speculative_for` in PBBS): runs
+/// steps `0..n` of `steps` in rounds of prefixes of the unfinished steps,
+/// sized by `policy`. Every round reserves and commits in parallel, then packs
+/// the failed steps in front of the untried ones.
+pub fn speculative_for<S: ReserveCommit>(
+    steps: &mut S,
+    n: usize,
+    policy: &RoundPolicy,
+) -> RoundStats {
+    let mut stats = RoundStats::default();
+    let mut remaining: Vec<usize> = (0..n).into_par_iter().collect();
+    let mut round_size = policy.first_size(n);
+
+    while !remaining.is_empty() {
+        let round = &remaining[..round_size.min(remaining.len())];
+        steps.start_round(round.len());
+
+        round.par_iter().for_each(|&i| steps.reserve(i));
+        let committed: Vec<bool> = round.par_iter().map(|&i| steps.commit(i)).collect();
+
+        let mut next = par_pack_range(round.len(), |j| (!committed[j]).then_some(round[j]));
+        let failed = next.len();
+        next.par_extend(remaining[round.len()..].par_iter().copied());
+        steps.finish_round(round);
+
+        stats.record(round.len(), failed);
+        round_size = policy.next_size(round.len(), failed, next.len());
+        remaining = next;
+    }
+    stats
+}
+
+mod test {
+    #[test]
+    fn greedy_matching_is_sequential() {
+        use super::{speculative_for, ReserveCommit, RoundPolicy};
+        use crate::utils::reservations::{DenseReservations, Reservations};
+        use rand::{Rng, SeedableRng};
+        use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering as AtomicOrdering};
+
+        /// Greedy maximal matching: edge `i` joins the matching if neither
+        /// endpoint is matched by an earlier edge.
+        struct Matching<'a> {
+            edges: &'a [(usize, usize)],
+            matched: Vec<AtomicBool>,
+            in_matching: Vec<AtomicBool>,
+            reservations: DenseReservations<AtomicUsize>,
+        }
+
+        impl Matching<'_> {
+            fn is_matched(&self, v: usize) -> bool {
+                self.matched[v].load(AtomicOrdering::Relaxed)
+            }
+        }
+
+        impl ReserveCommit for Matching<'_> {
+            fn reserve(&self, i: usize) {
+                let (u, v) = self.edges[i];
+                if !self.is_matched(u) && !self.is_matched(v) {
+                    self.reservations.reserve(u, i);
+                    self.reservations.reserve(v, i);
+                }
+            }
+
+            fn commit(&self, i: usize) -> bool {
+                let (u, v) = self.edges[i];
+                if self.reservations.holds(u, i) && self.reservations.holds(v, i) {
+                    self.matched[u].store(true, AtomicOrdering::Relaxed);
+                    self.matched[v].store(true, AtomicOrdering::Relaxed);
+                    self.in_matching[i].store(true, AtomicOrdering::Relaxed);
+                    true
+                } else {
+                    self.is_matched(u) || self.is_matched(v)
+                }
+            }
+
+            fn finish_round(&mut self, round: &[usize]) {
+                let touched: Vec<usize> = round
+                    .iter()
+                    .flat_map(|&i| [self.edges[i].0, self.edges[i].1])
+                    .collect();
+                self.reservations.finish_round(&touched);
+            }
+        }
+
+        let n = 1_000;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(25);
+        let edges: Vec<(usize, usize)> = (0..5_000)
+            .map(|_| (rng.gen_range(0..n), rng.gen_range(0..n)))
+            .filter(|(u, v)| u != v)
+            .collect();
+
+        let mut matched = vec![false; n];
+        let expected: Vec<bool> = edges
+            .iter()
+            .map(|&(u, v)| {
+                let joins = !matched[u] && !matched[v];
+                if joins {
+                    matched[u] = true;
+                    matched[v] = true;
+                }
+                joins
+            })
+            .collect();
+
+        [RoundPolicy::Fixed(64), RoundPolicy::default()]
+            .iter()
+            .for_each(|policy| {
+                let mut matching = Matching {
+                    edges: &edges,
+                    matched: (0..n).map(|_| AtomicBool::new(false)).collect(),
+                    in_matching: edges.iter().map(|_| AtomicBool::new(false)).collect(),
+                    reservations: DenseReservations::new(n),
+                };
+                let stats = speculative_for(&mut matching, edges.len(), policy);
+                let found: Vec<bool> = matching
+                    .in_matching
+                    .iter()
+                    .map(|b| b.load(AtomicOrdering::Relaxed))
+                    .collect();
+                assert_eq!(expected, found, "{:?}", policy);
+                assert!(stats.rounds() > 1);
+            });
+    }
+}
@@ -1,3 +1,9 @@
+pub mod counting_sort;
 pub mod cwslice;
+pub mod det_reservations;
 pub mod my_bencher;
+pub mod pack;
 pub mod prefix_scan;
+pub mod reservations;
+pub mod rng;
+pub mod select;
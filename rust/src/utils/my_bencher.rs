@@ -1,9 +1,14 @@
 use csv::Result;
+use rayon::iter::IntoParallelRefIterator;
 
 use crate::samplers::{
+    auto_sampler::AutoSampler,
+    hypergeometric_sampler::HypergeometricSampler,
     naive_sampler::NaiveSampler,
     perm_sampler::{FullPermutationSampler, PermutationSampler, SeqPermutationSampler},
     priority_sampler::{ParPrioritySampler, SeqPrioritySampler},
+    replacement_sampler::ReplacementSampler,
+    reservoir_sampler::ReservoirSampler,
     sampl_interface::Sampler,
 };
 
@@ -31,7 +36,7 @@ pub fn benchmark_core(wtr: &mut csv::Writer<std::fs::File>, core_count: usize) -
             if core_count > 1 {
                 println!("Naive {}", repeat + 1);
                 let start = std::time::Instant::now();
-                let _ = NaiveSampler::sample(&data, k);
+                let _ = NaiveSampler::new().sample(&data, k);
                 let end = std::time::Instant::now().duration_since(start);
                 wtr.write_record([
                     "Naive",
@@ -46,7 +51,7 @@ pub fn benchmark_core(wtr: &mut csv::Writer<std::fs::File>, core_count: usize) -
 
             println!("SeqPriority {}", repeat + 1);
             let start = std::time::Instant::now();
-            let _ = SeqPrioritySampler::sample(&data, k);
+            let _ = SeqPrioritySampler::new().sample(&data, k);
             let end = std::time::Instant::now().duration_since(start);
             wtr.write_record([
                 "SeqPriority",
@@ -60,7 +65,7 @@ pub fn benchmark_core(wtr: &mut csv::Writer<std::fs::File>, core_count: usize) -
 
             println!("ParPriority {}", repeat + 1);
             let start = std::time::Instant::now();
-            let _ = ParPrioritySampler::sample(&data, k);
+            let _ = ParPrioritySampler::new().sample(&data, k);
             let end = std::time::Instant::now().duration_since(start);
             wtr.write_record([
                 "ParPriority",
@@ -74,7 +79,7 @@ pub fn benchmark_core(wtr: &mut csv::Writer<std::fs::File>, core_count: usize) -
 
             println!("SeqPermutation {}", repeat + 1);
             let start = std::time::Instant::now();
-            let _ = SeqPermutationSampler::sample(&data, k);
+            let _ = SeqPermutationSampler::new().sample(&data, k);
             let end = std::time::Instant::now().duration_since(start);
             wtr.write_record([
                 "SeqPermutation",
@@ -88,7 +93,7 @@ pub fn benchmark_core(wtr: &mut csv::Writer<std::fs::File>, core_count: usize) -
 
             println!("FullPermutation {}", repeat + 1);
             let start = std::time::Instant::now();
-            let _ = FullPermutationSampler::sample(&data, k);
+            let _ = FullPermutationSampler::new().sample(&data, k);
             let end = std::time::Instant::now().duration_since(start);
             wtr.write_record([
                 "FullPermutation",
@@ -102,7 +107,7 @@ pub fn benchmark_core(wtr: &mut csv::Writer<std::fs::File>, core_count: usize) -
 
             println!("ParPermutation {}", repeat + 1);
             let start = std::time::Instant::now();
-            let _ = PermutationSampler::sample(&data, k);
+            let _ = PermutationSampler::new().sample(&data, k);
             let end = std::time::Instant::now().duration_since(start);
             wtr.write_record([
                 "ParPermutation",
@@ -113,6 +118,76 @@ pub fn benchmark_core(wtr: &mut csv::Writer<std::fs::File>, core_count: usize) -
                 "i32",
                 &end.as_millis().to_string(),
             ])?;
+
+            println!("Reservoir {}", repeat + 1);
+            let start = std::time::Instant::now();
+            let _ = ReservoirSampler::new().sample_iter(data.iter(), k);
+            let end = std::time::Instant::now().duration_since(start);
+            wtr.write_record([
+                "Reservoir",
+                &core_count.to_string(),
+                &PROBLEM_SIZE.to_string(),
+                &k.to_string(),
+                &repeat.to_string(),
+                "i32",
+                &end.as_millis().to_string(),
+            ])?;
+
+            println!("ParReservoir {}", repeat + 1);
+            let start = std::time::Instant::now();
+            let _ = ReservoirSampler::new().par_sample_iter(data.par_iter(), k);
+            let end = std::time::Instant::now().duration_since(start);
+            wtr.write_record([
+                "ParReservoir",
+                &core_count.to_string(),
+                &PROBLEM_SIZE.to_string(),
+                &k.to_string(),
+                &repeat.to_string(),
+                "i32",
+                &end.as_millis().to_string(),
+            ])?;
+
+            println!("Hypergeometric {}", repeat + 1);
+            let start = std::time::Instant::now();
+            let _ = HypergeometricSampler::new().sample(&data, k);
+            let end = std::time::Instant::now().duration_since(start);
+            wtr.write_record([
+                "Hypergeometric",
+                &core_count.to_string(),
+                &PROBLEM_SIZE.to_string(),
+                &k.to_string(),
+                &repeat.to_string(),
+                "i32",
+                &end.as_millis().to_string(),
+            ])?;
+
+            println!("Auto {}", repeat + 1);
+            let start = std::time::Instant::now();
+            let _ = AutoSampler::new().sample(&data, k);
+            let end = std::time::Instant::now().duration_since(start);
+            wtr.write_record([
+                "Auto",
+                &core_count.to_string(),
+                &PROBLEM_SIZE.to_string(),
+                &k.to_string(),
+                &repeat.to_string(),
+                "i32",
+                &end.as_millis().to_string(),
+            ])?;
+
+            println!("Replacement {}", repeat + 1);
+            let start = std::time::Instant::now();
+            let _ = ReplacementSampler::new().sample(&data, k);
+            let end = std::time::Instant::now().duration_since(start);
+            wtr.write_record([
+                "Replacement",
+                &core_count.to_string(),
+                &PROBLEM_SIZE.to_string(),
+                &k.to_string(),
+                &repeat.to_string(),
+                "i32",
+                &end.as_millis().to_string(),
+            ])?;
         }
     }
 
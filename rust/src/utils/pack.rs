@@ -0,0 +1,95 @@
+use std::mem::MaybeUninit;
+
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
+
+use crate::utils::{cwslice::UnsafeSlice, prefix_scan::par_scan};
+
+const BLOCK_SIZE: usize = 1 << 13;
+
+/// The `Some` results of `f` over `xs`, in order. Every block first counts
+/// its survivors, the counts are scanned into write offsets, and then every
+/// block writes its survivors straight into the output. So `f` runs twice
+/// per element and must give the same answer both times; no per-element
+/// flag vector is built.
+pub fn par_pack<T, U, F>(xs: &[T], f: F) -> Vec<U>
+where
+    T: Sync,
+    U: Send + Sync,
+    F: Fn(&T) -> Option<U> + Sync,
+{
+    par_pack_range(xs.len(), |i| f(&xs[i]))
+}
+
+/// `par_pack` over the indices `0..n`, for when `f` needs the position.
+pub fn par_pack_range<U, F>(n: usize, f: F) -> Vec<U>
+where
+    U: Send + Sync,
+    F: Fn(usize) -> Option<U> + Sync,
+{
+    let blocks = n.div_ceil(BLOCK_SIZE);
+    let block_range = |b: usize| b * BLOCK_SIZE..((b + 1) * BLOCK_SIZE).min(n);
+    let counts: Vec<usize> = (0..blocks)
+        .into_par_iter()
+        .map(|b| block_range(b).filter(|&i| f(i).is_some()).count())
+        .collect();
+    let (total, offsets) = par_scan(&counts, 0, |a, b| a + b);
+
+    let mut packed: Vec<U> = Vec::with_capacity(total);
+    let packed_slice = UnsafeSlice::new(&mut packed.spare_capacity_mut()[..total]);
+    (0..blocks)
+        .into_par_iter()
+        .zip(offsets.par_iter())
+        .zip(counts.par_iter())
+        .for_each(|((b, &offset), &count)| {
+            let written = block_range(b)
+                .filter_map(&f)
+                .take(count)
+                .enumerate()
+                .map(|(j, y)| unsafe { packed_slice.write(offset + j, MaybeUninit::new(y)) })
+                .count();
+            assert_eq!(
+                count, written,
+                "par_pack: f changed its answer between passes"
+            );
+        });
+
+    // SAFETY: the blocks wrote disjoint ranges that cover 0..total
+    unsafe {
+        packed.set_len(total);
+    }
+    packed
+}
+
+/// The elements of `xs` satisfying `pred`, in order.
+pub fn par_filter<T, P>(xs: &[T], pred: P) -> Vec<T>
+where
+    T: Clone + Send + Sync,
+    P: Fn(&T) -> bool + Sync,
+{
+    par_pack(xs, |x| pred(x).then(|| x.clone()))
+}
+
+mod test {
+    #[test]
+    fn pack_matches_filter() {
+        use super::{par_filter, par_pack};
+
+        [0, 10, 100_000].iter().for_each(|&n| {
+            let xs: Vec<usize> = (0..n).map(|i| (i * 7919) % 1_000).collect();
+            let expected: Vec<usize> = xs.iter().copied().filter(|&x| x < 300).collect();
+            assert_eq!(expected, par_filter(&xs, |&x| x < 300));
+
+            let expected: Vec<String> = xs
+                .iter()
+                .filter(|&&x| x % 7 == 0)
+                .map(|x| x.to_string())
+                .collect();
+            assert_eq!(
+                expected,
+                par_pack(&xs, |&x| (x % 7 == 0).then(|| x.to_string()))
+            );
+        });
+    }
+}
@@ -1,88 +1,128 @@
-use crate::utils::cwslice::UnsafeSlice;
-
-fn par_scan_up<'a>(xs: &'a [usize], aux: &UnsafeSlice<'a, usize>, aux_offset: usize) -> usize {
-    if xs.len() == 1 {
-        xs[0]
-    } else {
-        let m = xs.len() / 2;
-        let (xs_left, xs_right) = xs.split_at(m);
-        let (left, right) = rayon::join(
-            || par_scan_up(xs_left, aux, aux_offset),
-            || par_scan_up(xs_right, aux, aux_offset + m),
-        );
-        unsafe {
-            aux.write(aux_offset + m - 1, left);
-        }
-        left + right
-    }
-}
+use rayon::{
+    iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator},
+    slice::{ParallelSlice, ParallelSliceMut},
+};
 
-fn par_scan_down(
-    aux: &[usize],
-    res: &UnsafeSlice<'_, usize>,
-    ps: usize,
-    res_offset: usize,
-    res_size: usize,
-) {
-    if res_size == 1 {
-        unsafe {
-            res.write(res_offset, ps);
-        }
-    } else {
-        let m = res_size / 2;
-        let (aux_left, aux_right) = aux.split_at(m);
-        rayon::join(
-            || par_scan_down(aux_left, res, ps, res_offset, m),
-            || {
-                par_scan_down(
-                    aux_right,
-                    res,
-                    ps + aux[m - 1],
-                    res_offset + m,
-                    res_size - m,
-                )
-            },
-        );
-    }
+/// Inputs up to this long are scanned sequentially; longer ones are cut into
+/// blocks of this size, one task each.
+const BLOCK_SIZE: usize = 1 << 13;
+
+fn seq_scan_into<T: Copy, F: Fn(T, T) -> T>(
+    xs: &[T],
+    out: &mut [T],
+    offset: T,
+    op: &F,
+    inclusive: bool,
+) -> T {
+    xs.iter().zip(out).fold(offset, |acc, (&x, o)| {
+        let next = op(acc, x);
+        *o = if inclusive { next } else { acc };
+        next
+    })
 }
 
-pub fn par_scan(xs: &[usize]) -> (usize, Vec<usize>) {
-    // println!("Length is {}", xs.len());
-    if xs.is_empty() {
-        return (0, vec![]);
+/// Reduces every block, scans the block sums (recursively, but there are only
+/// `n / BLOCK_SIZE` of them) and then rescans every block from its offset.
+fn scan<T, F>(xs: &[T], identity: T, op: &F, inclusive: bool) -> (T, Vec<T>)
+where
+    T: Copy + Send + Sync,
+    F: Fn(T, T) -> T + Sync,
+{
+    let mut res = vec![identity; xs.len()];
+    if xs.len() <= BLOCK_SIZE {
+        let total = seq_scan_into(xs, &mut res, identity, op, inclusive);
+        return (total, res);
     }
 
-    let mut ell = vec![0; xs.len() - 1];
-    let ell_slice = UnsafeSlice::new(&mut ell);
-    let total = par_scan_up(xs, &ell_slice, 0);
+    let block_sums: Vec<T> = xs
+        .par_chunks(BLOCK_SIZE)
+        .map(|block| block.iter().fold(identity, |acc, &x| op(acc, x)))
+        .collect();
+    let (total, block_offsets) = scan(&block_sums, identity, op, false);
 
-    let mut res = vec![0; xs.len()];
-    let res_slice = UnsafeSlice::new(&mut res);
-    par_scan_down(&ell, &res_slice, 0, 0, xs.len());
+    res.par_chunks_mut(BLOCK_SIZE)
+        .zip(xs.par_chunks(BLOCK_SIZE))
+        .zip(block_offsets.par_iter())
+        .for_each(|((out, block), &offset)| {
+            seq_scan_into(block, out, offset, op, inclusive);
+        });
     (total, res)
 }
 
+/// Exclusive scan of `xs` under `op`, which must be associative with
+/// `identity` as its identity. Returns the total and the prefixes, where
+/// entry `i` combines `xs[..i]`. Floating-point sums are grouped by block, so
+/// they may differ from a sequential fold in the last bits.
+pub fn par_scan<T, F>(xs: &[T], identity: T, op: F) -> (T, Vec<T>)
+where
+    T: Copy + Send + Sync,
+    F: Fn(T, T) -> T + Sync,
+{
+    scan(xs, identity, &op, false)
+}
+
+/// Like `par_scan`, but entry `i` combines `xs[..=i]`, so the last entry is
+/// the total.
+pub fn par_scan_inclusive<T, F>(xs: &[T], identity: T, op: F) -> Vec<T>
+where
+    T: Copy + Send + Sync,
+    F: Fn(T, T) -> T + Sync,
+{
+    scan(xs, identity, &op, true).1
+}
+
 mod test {
     #[test]
     fn prefix_sum() {
         use super::par_scan;
         use rand::seq::SliceRandom;
 
-        let sample_size = 1_000;
-        let mut population = (0..sample_size).collect::<Vec<usize>>();
-        let mut rng = rand::thread_rng();
-        population.shuffle(&mut rng);
+        [1_000, 100_000].iter().for_each(|&sample_size| {
+            let mut population = (0..sample_size).collect::<Vec<usize>>();
+            let mut rng = rand::thread_rng();
+            population.shuffle(&mut rng);
+
+            let mut acc = 0;
+            let mut seq_ps: Vec<usize> = Vec::with_capacity(sample_size);
+            population.iter().for_each(|elm| {
+                seq_ps.push(acc);
+                acc += elm;
+            });
 
-        let mut acc = 0;
-        let mut seq_ps: Vec<usize> = Vec::with_capacity(sample_size);
-        population.iter().for_each(|elm| {
-            seq_ps.push(acc);
-            acc += elm;
+            let (ps, partials) = par_scan(&population, 0, |a, b| a + b);
+
+            assert_eq!(acc, ps);
+            assert_eq!(&seq_ps, &partials);
         });
+    }
+
+    #[test]
+    fn inclusive_scan_other_types() {
+        use super::{par_scan, par_scan_inclusive};
+
+        let flags: Vec<u32> = (0..50_000).map(|i| (i % 3 == 0) as u32).collect();
+        let counts = par_scan_inclusive(&flags, 0, |a, b| a + b);
+        assert_eq!(16_667, *counts.last().unwrap());
+        assert!(counts
+            .iter()
+            .enumerate()
+            .all(|(i, &c)| c as usize == i / 3 + 1));
 
-        let (ps, partials) = par_scan(&population);
+        let weights: Vec<f64> = (0..50_000).map(|i| 0.5 + (i % 4) as f64).collect();
+        let (total, cumulative) = par_scan(&weights, 0.0, |a, b| a + b);
+        let seq_total: f64 = weights.iter().sum();
+        assert!((total - seq_total).abs() < 1e-9 * seq_total);
+        assert_eq!(0.0, cumulative[0]);
+
+        let running_max = par_scan_inclusive(&weights, f64::NEG_INFINITY, f64::max);
+        assert_eq!(3.5, running_max[3]);
+        assert_eq!(0.5, running_max[0]);
+    }
+
+    #[test]
+    fn scan_empty() {
+        use super::par_scan;
 
-        assert_eq!(acc, ps);
-        assert_eq!(&seq_ps, &partials);
+        assert_eq!((0, vec![]), par_scan(&[] as &[usize], 0, |a, b| a + b));
     }
 }
@@ -0,0 +1,261 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering as AtomicOrdering};
+
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
+
+const EMPTY: usize = usize::MAX;
+
+/// Slots per entry kept by `MinTable`, so probe chains stay short.
+const TABLE_SLACK: usize = 2;
+
+/// Reservation state for deterministic reservations: every slot remembers
+/// the smallest index that reserved it during the current round.
+pub trait Reservations: Sync {
+    /// Called before a round in which `round_len` indices reserve.
+    fn start_round(&mut self, _round_len: usize) {}
+
+    fn reserve(&self, slot: usize, i: usize);
+
+    /// Whether `i` is the smallest index that reserved `slot` this round.
+    fn holds(&self, slot: usize, i: usize) -> bool;
+
+    /// Whether `finish_round` needs the slots of the round; if not, callers
+    /// may skip collecting them and pass an empty slice.
+    fn needs_touched(&self) -> bool {
+        true
+    }
+
+    /// Clears the reservations of a finished round, given every slot it
+    /// may have touched.
+    fn finish_round(&mut self, _slots: &[usize]) {}
+}
+
+/// Integer atomics a `DenseReservations` can be built from.
+pub trait AtomicSlot: Send + Sync {
+    fn empty() -> Self;
+    fn fetch_min(&self, i: usize);
+    fn get(&self) -> usize;
+    fn clear(&self);
+}
+
+impl AtomicSlot for AtomicUsize {
+    fn empty() -> Self {
+        AtomicUsize::new(EMPTY)
+    }
+
+    fn fetch_min(&self, i: usize) {
+        AtomicUsize::fetch_min(self, i, AtomicOrdering::Relaxed);
+    }
+
+    fn get(&self) -> usize {
+        self.load(AtomicOrdering::Relaxed)
+    }
+
+    fn clear(&self) {
+        self.store(EMPTY, AtomicOrdering::Relaxed);
+    }
+}
+
+/// Half the memory of `AtomicUsize`, for indices below `u32::MAX`.
+impl AtomicSlot for AtomicU32 {
+    fn empty() -> Self {
+        AtomicU32::new(u32::MAX)
+    }
+
+    fn fetch_min(&self, i: usize) {
+        AtomicU32::fetch_min(self, i as u32, AtomicOrdering::Relaxed);
+    }
+
+    fn get(&self) -> usize {
+        self.load(AtomicOrdering::Relaxed) as usize
+    }
+
+    fn clear(&self) {
+        self.store(u32::MAX, AtomicOrdering::Relaxed);
+    }
+}
+
+/// One atomic per slot, allocated once; a round only clears the slots it
+/// touched.
+pub struct DenseReservations<A: AtomicSlot> {
+    slots: Vec<A>,
+}
+
+impl<A: AtomicSlot> DenseReservations<A> {
+    pub fn new(n: usize) -> Self {
+        Self {
+            slots: (0..n).into_par_iter().map(|_| A::empty()).collect(),
+        }
+    }
+}
+
+impl<A: AtomicSlot> Reservations for DenseReservations<A> {
+    fn reserve(&self, slot: usize, i: usize) {
+        self.slots[slot].fetch_min(i);
+    }
+
+    fn holds(&self, slot: usize, i: usize) -> bool {
+        self.slots[slot].get() == i
+    }
+
+    fn finish_round(&mut self, slots: &[usize]) {
+        slots.par_iter().for_each(|&slot| self.slots[slot].clear());
+    }
+}
+
+/// Lock-free open-addressing map from keys to the smallest value inserted
+/// with them. Holds at most `capacity` keys, and keys must not be
+/// `usize::MAX`.
+pub struct MinTable {
+    keys: Vec<AtomicUsize>,
+    mins: Vec<AtomicUsize>,
+    mask: usize,
+}
+
+impl MinTable {
+    pub fn with_capacity(capacity: usize) -> Self {
+        let size = (TABLE_SLACK * capacity).next_power_of_two().max(16);
+        Self {
+            keys: (0..size)
+                .into_par_iter()
+                .map(|_| AtomicUsize::empty())
+                .collect(),
+            mins: (0..size)
+                .into_par_iter()
+                .map(|_| AtomicUsize::empty())
+                .collect(),
+            mask: size - 1,
+        }
+    }
+
+    fn home(&self, key: usize) -> usize {
+        fxhash::hash64(&key) as usize & self.mask
+    }
+
+    /// Lowers the value of `key` to `value`; true if `key` is new.
+    pub fn insert_min(&self, key: usize, value: usize) -> bool {
+        let mut slot = self.home(key);
+        loop {
+            match self.keys[slot].compare_exchange(
+                EMPTY,
+                key,
+                AtomicOrdering::AcqRel,
+                AtomicOrdering::Acquire,
+            ) {
+                Ok(_) => {
+                    self.mins[slot].fetch_min(value, AtomicOrdering::Relaxed);
+                    return true;
+                }
+                Err(existing) if existing == key => {
+                    self.mins[slot].fetch_min(value, AtomicOrdering::Relaxed);
+                    return false;
+                }
+                Err(_) => slot = (slot + 1) & self.mask,
+            }
+        }
+    }
+
+    pub fn get(&self, key: usize) -> Option<usize> {
+        let mut slot = self.home(key);
+        loop {
+            match self.keys[slot].load(AtomicOrdering::Acquire) {
+                EMPTY => return None,
+                found if found == key => return Some(self.mins[slot].get()),
+                _ => slot = (slot + 1) & self.mask,
+            }
+        }
+    }
+
+    /// `(key, smallest value)` for every key in the table, in no order.
+    pub fn entries(&self) -> Vec<(usize, usize)> {
+        self.keys
+            .par_iter()
+            .zip(self.mins.par_iter())
+            .filter_map(|(key, min)| {
+                let key = key.get();
+                (key != EMPTY).then(|| (key, min.get()))
+            })
+            .collect()
+    }
+}
+
+/// Keeps only the slots touched in the current round, in a `MinTable` sized
+/// for the round. Memory is proportional to the round, not to `n`.
+#[derive(Default)]
+pub struct SparseReservations {
+    table: Option<MinTable>,
+}
+
+impl SparseReservations {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn table(&self) -> &MinTable {
+        self.table
+            .as_ref()
+            .expect("SparseReservations used outside of a round")
+    }
+}
+
+impl Reservations for SparseReservations {
+    /// Every index reserves at most two slots.
+    fn start_round(&mut self, round_len: usize) {
+        self.table = Some(MinTable::with_capacity(2 * round_len));
+    }
+
+    fn reserve(&self, slot: usize, i: usize) {
+        self.table().insert_min(slot, i);
+    }
+
+    fn holds(&self, slot: usize, i: usize) -> bool {
+        self.table().get(slot) == Some(i)
+    }
+
+    /// The whole table goes at the end of the round.
+    fn needs_touched(&self) -> bool {
+        false
+    }
+
+    fn finish_round(&mut self, _slots: &[usize]) {
+        self.table = None;
+    }
+}
+
+mod test {
+    #[test]
+    fn reservations_agree() {
+        use super::{DenseReservations, Reservations, SparseReservations};
+        use std::sync::atomic::{AtomicU32, AtomicUsize};
+
+        fn run<R: Reservations>(mut reservations: R) -> Vec<bool> {
+            let n = 1_000;
+            let targets: Vec<usize> = (0..n).map(|i| (i * 7_919) % n).collect();
+            (0..3)
+                .flat_map(|round| {
+                    let indices: Vec<usize> = (round * 100..round * 100 + 200).collect();
+                    reservations.start_round(indices.len());
+                    indices.iter().for_each(|&i| {
+                        reservations.reserve(i, i);
+                        reservations.reserve(targets[i], i);
+                    });
+                    let held: Vec<bool> = indices
+                        .iter()
+                        .map(|&i| reservations.holds(i, i) && reservations.holds(targets[i], i))
+                        .collect();
+                    let touched: Vec<usize> =
+                        indices.iter().flat_map(|&i| [i, targets[i]]).collect();
+                    reservations.finish_round(&touched);
+                    held
+                })
+                .collect()
+        }
+
+        let dense = run(DenseReservations::<AtomicUsize>::new(1_000));
+        assert_eq!(dense, run(DenseReservations::<AtomicU32>::new(1_000)));
+        assert_eq!(dense, run(SparseReservations::new()));
+        assert!(dense.iter().any(|&held| held));
+        assert!(dense.iter().any(|&held| !held));
+    }
+}
@@ -0,0 +1,85 @@
+use rand::{Error, Rng, RngCore, SeedableRng};
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+fn mix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Small, fast SplitMix64 generator. Cheap enough to construct one per index,
+/// which is what makes the parallel samplers reproducible.
+#[derive(Clone, Debug)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl RngCore for SplitMix64 {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix64(self.state)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        dest.chunks_mut(8).for_each(|chunk| {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        });
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+impl SeedableRng for SplitMix64 {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        Self {
+            state: u64::from_le_bytes(seed),
+        }
+    }
+
+    fn seed_from_u64(state: u64) -> Self {
+        Self { state }
+    }
+}
+
+/// Counter-based stream for index `i`: the values only depend on `seed` and
+/// `i`, never on which thread (or in which order) the index gets processed.
+pub fn index_rng(seed: u64, i: usize) -> SplitMix64 {
+    SplitMix64 {
+        state: mix64(seed ^ mix64((i as u64).wrapping_add(GOLDEN_GAMMA))),
+    }
+}
+
+/// Uniform on (0, 1], so its log is finite.
+pub fn open_unit<R: Rng + ?Sized>(rng: &mut R) -> f64 {
+    1.0 - rng.gen::<f64>()
+}
+
+mod test {
+    #[test]
+    fn index_streams_are_reproducible() {
+        use super::index_rng;
+        use rand::RngCore;
+
+        let first: Vec<u64> = (0..1_000).map(|i| index_rng(42, i).next_u64()).collect();
+        let second: Vec<u64> = (0..1_000)
+            .rev()
+            .map(|i| index_rng(42, i).next_u64())
+            .rev()
+            .collect();
+        assert_eq!(first, second);
+
+        let other_seed: Vec<u64> = (0..1_000).map(|i| index_rng(43, i).next_u64()).collect();
+        assert_ne!(first, other_seed);
+    }
+}
@@ -0,0 +1,141 @@
+use rand::{Rng, SeedableRng};
+use rayon::{
+    iter::{IntoParallelRefIterator, ParallelIterator},
+    slice::ParallelSliceMut,
+};
+
+use crate::utils::{pack::par_pack, rng::SplitMix64};
+
+/// Inputs up to this long are finished with a sequential select.
+const SEQ_CUTOFF: usize = 1 << 14;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Side {
+    Below,
+    Between,
+    Above,
+}
+
+/// How many elements fall below, between and above the pivots.
+type Counts = (usize, usize, usize);
+
+/// The element of rank `k` (0-based) when `xs` is ordered by `key`, like
+/// `parlay::kth_smallest`. Among equal keys, any of them may come back.
+///
+/// Every round draws about `n^(2/3)` elements, sorts them and takes two
+/// pivots from the sample that bracket rank `k` with high probability
+/// (Floyd–Rivest), then splits the input into below / between / above the
+/// pivots with a counting pass and a `par_pack` of the kept side. Almost always only the
+/// middle part, about `n^(2/3)` long, survives. Once the input is short
+/// enough, it is finished sequentially.
+///
+/// # Panics
+/// If `k >= xs.len()`.
+pub fn par_kth_smallest<T, K, F>(xs: &[T], k: usize, key: F) -> &T
+where
+    T: Sync,
+    K: Ord + Sync,
+    F: Fn(&T) -> K + Sync,
+{
+    assert!(
+        k < xs.len(),
+        "par_kth_smallest: rank {k} out of bounds for length {}",
+        xs.len()
+    );
+
+    let mut rng = SplitMix64::seed_from_u64(xs.len() as u64);
+    let mut items: Vec<&T> = xs.par_iter().collect();
+    let mut rank = k;
+    // a round that kept everything falls back to a single pivot next
+    let mut stuck = false;
+
+    while items.len() > SEQ_CUTOFF {
+        let n = items.len();
+        let s = ((n as f64).powf(2.0 / 3.0) as usize).max(1);
+        let mut sample: Vec<&T> = (0..s).map(|_| items[rng.gen_range(0..n)]).collect();
+        sample.par_sort_unstable_by(|a, b| key(a).cmp(&key(b)));
+
+        let target = rank * s / n;
+        let delta = if stuck {
+            0
+        } else {
+            2 * (s as f64).sqrt() as usize
+        };
+        let lo = sample[target.saturating_sub(delta)];
+        let hi = sample[(target + delta).min(s - 1)];
+        let (lo_key, hi_key) = (key(lo), key(hi));
+        let side = |x: &T| {
+            let x_key = key(x);
+            if x_key < lo_key {
+                Side::Below
+            } else if x_key > hi_key {
+                Side::Above
+            } else {
+                Side::Between
+            }
+        };
+
+        let (below, between, _): Counts = items
+            .par_iter()
+            .map(|x| match side(x) {
+                Side::Below => (1, 0, 0),
+                Side::Between => (0, 1, 0),
+                Side::Above => (0, 0, 1),
+            })
+            .reduce(|| (0, 0, 0), |x, y| (x.0 + y.0, x.1 + y.1, x.2 + y.2));
+
+        let keep = if rank < below {
+            Side::Below
+        } else if rank < below + between {
+            if lo_key == hi_key {
+                return lo;
+            }
+            rank -= below;
+            Side::Between
+        } else {
+            rank -= below + between;
+            Side::Above
+        };
+
+        items = par_pack(&items, |&x| (side(x) == keep).then_some(x));
+        stuck = items.len() == n;
+    }
+
+    items
+        .select_nth_unstable_by(rank, |a, b| key(a).cmp(&key(b)))
+        .1
+}
+
+mod test {
+    #[test]
+    fn kth_matches_sort() {
+        use super::par_kth_smallest;
+        use rand::{seq::SliceRandom, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        [1, 100, 50_000, 1_000_000].iter().for_each(|&n| {
+            let mut xs: Vec<usize> = (0..n).collect();
+            xs.shuffle(&mut rng);
+            [0, n / 3, n - 1].iter().for_each(|&k| {
+                assert_eq!(k, *par_kth_smallest(&xs, k, |&x| x));
+                assert_eq!(n - 1 - k, *par_kth_smallest(&xs, k, |&x| n - x));
+            });
+        });
+    }
+
+    #[test]
+    fn kth_with_many_ties() {
+        use super::par_kth_smallest;
+
+        let n = 200_000;
+        let xs: Vec<(u8, usize)> = (0..n).map(|i| ((i % 3) as u8, i)).collect();
+        let mut sorted: Vec<u8> = xs.iter().map(|x| x.0).collect();
+        sorted.sort_unstable();
+        [0, 1, n / 3, n / 2, n - 1].iter().for_each(|&k| {
+            assert_eq!(sorted[k], par_kth_smallest(&xs, k, |x| x.0).0);
+        });
+
+        let constant = vec![7; n];
+        assert_eq!(7, *par_kth_smallest(&constant, n / 2, |&x| x));
+    }
+}